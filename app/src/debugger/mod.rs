@@ -10,14 +10,16 @@ mod screen;
 use self::fakecontroller::FakeController;
 use self::opcodes::OpCodes;
 use nes::input::standard_controller::StandardController;
-use nes::memory::Memory;
+use nes::memory::{Memory, RamPattern};
 use nes::ppu::attributetable;
 use nes::ppu::screen::{Screen, ScreenMock, COLOUR_PALETTE};
 use nes::ppu::sprite::Sprite;
+use nes::ppu::viewer;
+use nes::sound::recorder::WavRecorder;
 use nes::sound::AudioDevice;
 use nes::NES;
 use nes_sdl2::standard_controller::SdlEvents;
-use nes_sdl2::SDL2;
+use nes_sdl2::{Viewers, SDL2};
 
 use std::env;
 use std::fs::File;
@@ -53,27 +55,28 @@ pub fn start() {
         .find(|&a| a == "-g")
         .map(|_| true)
         .unwrap_or(false);
+    let viewers = args.iter().find(|&a| a == "-v").map(|_| sdl.viewers());
 
     if use_screen_mock {
         let screen = box screen::NoScreen(());
         let nes = nes::NES::from_file(
             file,
             MutableRef::Borrowed(&mut standard_controller),
-            sdl.audio(),
+            WavRecorder::new(sdl.audio()),
             screen,
         );
 
-        run(nes, &source, &fake_controller);
+        run(nes, &source, &fake_controller, viewers);
     } else {
         let screen = box sdl.screen(2);
         let nes = nes::NES::from_file(
             file,
             MutableRef::Borrowed(&mut standard_controller),
-            sdl.audio(),
+            WavRecorder::new(sdl.audio()),
             screen,
         );
 
-        run(nes, &source, &fake_controller);
+        run(nes, &source, &fake_controller, viewers);
     }
 }
 
@@ -87,44 +90,23 @@ fn open_log_file() -> File {
     return File::create(file_name).unwrap();
 }
 
-use nes::cpu::*;
-
 #[inline]
-fn log<'a, S, A>(log_file: &Option<File>, nes: &NES<'a, S, A>, opcodes: Rc<OpCodes>)
+fn log<'a, S, A>(log_file: &Option<File>, nes: &NES<'a, S, A>)
 where
     S: Screen + Sized,
     A: AudioDevice + Sized,
 {
     for mut file in log_file.iter() {
-        let flags: String = [
-            (NEGATIVE_FLAG, 'N'),
-            (OVERFLOW_FLAG, 'O'),
-            (DECIMAL_FLAG, 'D'),
-            (INTERRUPT_DISABLE_FLAG, 'I'),
-            (ZERO_FLAG, 'Z'),
-            (CARRY_FLAG, 'C'),
-        ]
-        .iter()
-        .filter(|&&(f, _)| nes.cpu.is_flag_set(f))
-        .map(|&(_, f)| f)
-        .collect();
-        file.write_fmt(format_args!(
-            "Cycle: {} - {} - A: {}, X: {}, Y: {}, Flags: {}\n",
-            nes.cycle_count,
-            next_instruction_as_string(&nes, opcodes.clone()),
-            nes.cpu.accumulator(),
-            nes.cpu.register_x(),
-            nes.cpu.register_y(),
-            flags
-        ))
-        .unwrap();
+        file.write_fmt(format_args!("{}\n", nes::trace::trace(nes)))
+            .unwrap();
     }
 }
 
 fn run<'a, S, A>(
-    mut nes: NES<'a, S, A>,
+    mut nes: NES<'a, S, WavRecorder<A>>,
     source: &SdlEvents,
     fake_controller: &Option<FakeController>,
+    mut viewers: Option<Viewers>,
 ) where
     S: Screen + Sized,
     A: AudioDevice + Sized,
@@ -135,6 +117,9 @@ fn run<'a, S, A>(
     print(&nes);
     print_next_instruction(&nes, opcodes.clone());
     loop {
+        if let Some(ref mut viewers) = viewers {
+            viewers.show(&nes.ppu.borrow());
+        }
         print!(">");
         io::stdout().flush().unwrap();
         let cmd: Command = read_input();
@@ -147,8 +132,11 @@ fn run<'a, S, A>(
                 let mut should_exit = false;
                 let mut counter = 0;
                 while (cycles == 0 || nes.cycle_count < end_cycle) && !should_exit {
-                    log(&log_file, &nes, opcodes.clone());
+                    log(&log_file, &nes);
                     nes.execute();
+                    if let Some(ref mut viewers) = viewers {
+                        viewers.update(&nes.ppu.borrow());
+                    }
                     counter += 1;
                     if counter > 0x100_000 {
                         should_exit = source.should_exit();
@@ -164,12 +152,12 @@ fn run<'a, S, A>(
                 let arg: u32 = cmd.arg(1).and_then(|s| s.parse::<u32>().ok()).unwrap_or(1);
                 if arg > 1 {
                     for _ in 0..(arg) {
-                        log(&log_file, &nes, opcodes.clone());
+                        log(&log_file, &nes);
                         nes.execute();
                         println!("Cycle count: {}", nes.cycle_count);
                     }
                 } else {
-                    log(&log_file, &nes, opcodes.clone());
+                    log(&log_file, &nes);
                     nes.execute();
                 }
                 print(&nes);
@@ -193,6 +181,21 @@ fn run<'a, S, A>(
                     None => println!("Please specify address"),
                 };
             },
+            "reset" => {
+                nes.reset();
+                print(&nes);
+                print_next_instruction(&nes, opcodes.clone());
+            },
+            "power-cycle" => {
+                match cmd.arg(1).map(|s| s.parse::<RamPattern>()).unwrap_or(Ok(RamPattern::Zeros)) {
+                    Ok(ram_pattern) => {
+                        nes.power_cycle(ram_pattern);
+                        print(&nes);
+                        print_next_instruction(&nes, opcodes.clone());
+                    },
+                    Err(e) => println!("{}", e),
+                }
+            },
             "trace" => {
                 log_file = Some(open_log_file());
             },
@@ -203,10 +206,15 @@ fn run<'a, S, A>(
                         for address in pattern..(pattern+8) {
                             let layer1 = nes.ppu.borrow().memory().get(address, 0);
                             let layer2 = nes.ppu.borrow().memory().get(address+8, 0);
+                            let result: String = (0..8).rev()
+                                .map(|bit| (((layer2 >> bit) & 0x01) << 1) | ((layer1 >> bit) & 0x01))
+                                .map(|colour| colour.to_string())
+                                .collect();
                             println!(
-                                "{:08b}\t{:08b}\tNOT IMPLEMENTED",
+                                "{:08b}\t{:08b}\t{}",
                                 layer1,
-                                layer2
+                                layer2,
+                                result
                             );
                         }
                     },
@@ -270,10 +278,9 @@ fn run<'a, S, A>(
                 }
             },
             "screenshot" => {
-                let mut ppu = nes.ppu.borrow_mut();
+                let ppu = nes.ppu.borrow();
                 let mut screen = ScreenMock::new();
-                ppu.invalidate_tile_cache();
-                ppu.update_screen(&mut screen);
+                ppu.present(&mut screen);
                 let now: DateTime<Local> = Local::now();
                 let file_name = format!("/tmp/rustiness.{:?}.png", now);
 
@@ -284,14 +291,76 @@ fn run<'a, S, A>(
                     240,
                     image::RGB(8)
                 ).unwrap();
-                let background_file_name = format!("/tmp/rustiness.{:?}.bg.png", now);
-                image::save_buffer(
-                    &Path::new(&background_file_name),
-                    screen.temp_buffer.as_ref(),
-                    256*2,
-                    240*2,
-                    image::RGB(8)
-                ).unwrap();
+            },
+            "image" => {
+                let ppu = nes.ppu.borrow();
+                let image = match cmd.arg(1).map(|s| s.as_str()) {
+                    Some("name-tables") => Some(viewer::name_tables(&ppu)),
+                    Some("pattern-tables") => {
+                        let palette: u8 = cmd.arg(2).and_then(|s| s.parse::<u8>().ok()).unwrap_or(0);
+                        Some(viewer::pattern_tables(&ppu, palette))
+                    },
+                    Some("palette") => Some(viewer::palette(&ppu)),
+                    Some("sprites") => Some(viewer::sprites(&ppu)),
+                    Some("events") => Some(viewer::events(&ppu)),
+                    _ => None,
+                };
+                match image {
+                    Some(image) => {
+                        let now: DateTime<Local> = Local::now();
+                        let file_name = format!("/tmp/rustiness.{}.{:?}.png", cmd.arg(1).unwrap(), now);
+                        image::save_buffer(
+                            &Path::new(&file_name),
+                            &image.rgba,
+                            image.width as u32,
+                            image.height as u32,
+                            image::RGBA(8)
+                        ).unwrap();
+                        println!("Saved {}", file_name);
+                    },
+                    None => println!("Usage: image name-tables|pattern-tables [PALETTE]|palette|sprites|events"),
+                }
+            },
+            "events" => {
+                match cmd.arg(1).map(|s| s.as_str()) {
+                    Some("on") => nes.set_event_logging(true),
+                    Some("off") => nes.set_event_logging(false),
+                    _ => {
+                        match nes.ppu.borrow().events() {
+                            Some(events) => {
+                                println!("Last frame:");
+                                for event in events.last_frame() {
+                                    println!("{}", event);
+                                }
+                                println!("Current frame:");
+                                for event in events.current_frame() {
+                                    println!("{}", event);
+                                }
+                            },
+                            None => println!("Event logging is off, turn it on with 'events on'"),
+                        }
+                    }
+                }
+            },
+            "record" => {
+                let recorder = nes.apu.audio_device();
+                match (cmd.arg(1).map(|s| s.as_str()), cmd.arg(2)) {
+                    (Some("start"), Some(file)) => {
+                        let stems = cmd.arg(3).map(|s| s == "stems").unwrap_or(false);
+                        match recorder.start(file, stems) {
+                            Ok(()) => println!("Recording to {}", file),
+                            Err(e) => println!("Unable to record to {}: {}", file, e),
+                        }
+                    },
+                    (Some("stop"), None) if recorder.is_recording() => {
+                        match recorder.stop() {
+                            Ok(()) => println!("Recording stopped"),
+                            Err(e) => println!("Unable to finish the recording: {}", e),
+                        }
+                    },
+                    (Some("stop"), None) => println!("Not recording"),
+                    _ => println!("Usage: record start FILE [stems]|stop"),
+                }
             },
             "stack" => {
                 let mut entries: u8 = cmd.arg(1).and_then(|s| s.parse::<u8>().ok()).unwrap_or(5);
@@ -1,44 +1,180 @@
+extern crate getopts;
+use self::getopts::Options;
+
 use nes::borrow::MutableRef;
+use nes::ines::mapper;
 use nes::input::standard_controller::StandardController;
+use nes::memory::RamPattern;
+use nes::ppu::screen::{ColourPalette, NtscParameters};
+use nes::region::Region;
+use nes::sound::recorder::WavRecorder;
+use nes_sdl2::standard_controller::HotKey;
 use nes_sdl2::SDL2;
 use std::env;
+use std::process::exit;
 
-use nes::NES;
+use nes::{Settings, NES};
 pub fn start() {
     let args: Vec<String> = env::args().collect();
-    if args.len() < 3 {
-        panic!("Usage: {} run FILE", args[0]);
+    let mut opts = Options::new();
+    opts.optopt(
+        "r",
+        "ram",
+        "RAM contents after a power cycle (zeros, ones or random)",
+        "PATTERN",
+    );
+    opts.optflag(
+        "",
+        "no-sprite-limit",
+        "Draw all sprites on a scanline instead of at most 8, reduces flickering",
+    );
+    opts.optopt(
+        "",
+        "region",
+        "Timing to use instead of the one in the header (ntsc, pal or dendy)",
+        "REGION",
+    );
+    opts.optflagopt(
+        "",
+        "audio-pacing",
+        "Let the audio queue set the speed, keeping MILLIS of audio queued (default 40)",
+        "MILLIS",
+    );
+    opts.optopt("", "record", "Record the audio to a WAV file", "FILE");
+    opts.optflag(
+        "",
+        "stems",
+        "Record each channel to its own file as well, named after the channel",
+    );
+    opts.optflag(
+        "",
+        "viewers",
+        "Show the name tables, pattern tables, palette and sprites in separate windows",
+    );
+    opts.optopt(
+        "p",
+        "palette",
+        "Colours to use, read from a .pal file",
+        "FILE",
+    );
+    opts.optflagopt(
+        "",
+        "ntsc-palette",
+        "Generate the colours from the NTSC signal instead",
+        "HUE,SATURATION,CONTRAST,BRIGHTNESS,GAMMA",
+    );
+    let matches = match opts.parse(&args[2..]) {
+        Ok(m) => m,
+        Err(f) => panic!(f.to_string()),
+    };
+    if matches.free.is_empty() {
+        panic!("{}", opts.short_usage(&format!("{} run FILE", args[0])));
     }
+    let ram_pattern = matches
+        .opt_str("r")
+        .map(|p| or_exit(p.parse::<RamPattern>()))
+        .unwrap_or(RamPattern::Zeros);
 
-    let file = &args[2];
+    let file = &matches.free[0];
     let sdl = SDL2::new();
 
     let source = sdl.event_pump();
     let mut standard_controller = StandardController::new(&source);
 
+    let palette = if matches.opt_present("ntsc-palette") {
+        let parameters = matches
+            .opt_str("ntsc-palette")
+            .map(|p| or_exit(p.parse::<NtscParameters>()))
+            .unwrap_or_default();
+        ColourPalette::generate(&parameters)
+    } else {
+        matches
+            .opt_str("p")
+            .map(|file| or_exit(ColourPalette::from_file(&file)))
+            .unwrap_or_default()
+    };
+
+    let settings = Settings {
+        sprite_limit: !matches.opt_present("no-sprite-limit"),
+        palette: palette,
+        region: matches
+            .opt_str("region")
+            .map(|r| or_exit(r.parse::<Region>())),
+        audio_pacing: if matches.opt_present("audio-pacing") {
+            Some(
+                matches
+                    .opt_str("audio-pacing")
+                    .map(|m| {
+                        or_exit(
+                            m.parse::<u32>()
+                                .map_err(|e| format!("Invalid audio latency '{}': {}", m, e)),
+                        )
+                    })
+                    .unwrap_or(40),
+            )
+        } else {
+            None
+        },
+    };
+
+    let recorder = WavRecorder::new(sdl.audio());
+    if let Some(record) = matches.opt_str("record") {
+        or_exit(
+            recorder
+                .start(&record, matches.opt_present("stems"))
+                .map_err(|e| format!("Could not record to '{}': {}", record, e)),
+        );
+    }
+
     let screen = box sdl.screen(2);
-    let nes = nes::NES::from_file(
-        file,
+    let nes = nes::NES::with_settings(
+        mapper::from_file(file),
         MutableRef::Borrowed(&mut standard_controller),
-        sdl.audio(),
+        recorder,
         screen,
+        settings,
     );
 
-    run(nes, &source);
+    let viewers = if matches.opt_present("viewers") {
+        Some(sdl.viewers())
+    } else {
+        None
+    };
+
+    run(nes, &source, ram_pattern, viewers);
+}
+use nes_sdl2::{SDL2Screen, Viewers};
+
+/**
+ * The value of a successfully parsed option, or exits with the error.
+ */
+fn or_exit<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1)
+    })
 }
-use nes_sdl2::SDL2Screen;
 
 fn run<'a>(
-    mut nes: NES<'a, SDL2Screen, nes_sdl2::SDLAudioDevice>,
+    mut nes: NES<'a, SDL2Screen, WavRecorder<nes_sdl2::SDLAudioDevice>>,
     source: &nes_sdl2::standard_controller::SdlEvents,
+    ram_pattern: RamPattern,
+    mut viewers: Option<Viewers>,
 ) {
     let mut counter = 0;
     loop {
         nes.execute();
+        if let Some(ref mut viewers) = viewers {
+            viewers.update(&nes.ppu.borrow());
+        }
         counter += 1;
-        if counter > 0x100_000 {
-            if source.should_exit() {
-                return;
+        if counter > 0x10_000 {
+            for hot_key in source.hot_keys() {
+                match hot_key {
+                    HotKey::Exit => return,
+                    HotKey::Reset => nes.reset(),
+                    HotKey::PowerCycle => nes.power_cycle(ram_pattern),
+                }
             }
             counter = 0;
         }
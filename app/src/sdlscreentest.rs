@@ -1,4 +1,4 @@
-use nes::ppu::screen::{Pattern, Screen};
+use nes::ppu::screen::{ColourPalette, Frame, Pattern, Screen, SCREEN_HEIGHT, SCREEN_WIDTH};
 use nes_sdl2::SDL2;
 
 use nes::input::standard_controller::Source;
@@ -157,138 +157,63 @@ pub fn start() {
     std::thread::sleep(Duration::from_millis(2000));
     use std::time::Instant;
 
-    screen.update_buffer(|buffer| {
-        for tile_y in 0..30 {
-            let mut tile_x = 0;
-            for tile in 0..32 {
-                let pixels = patterns[(tile % 10) + 1].data;
-                for row in 0..8 {
-                    for col in 0..8 {
-                        let colour = pixels[row][col] * 255;
-                        if colour == 0 {
-                            buffer.set_pixel(col + tile_x, row + (tile_y * 8), (0, 0, 0, 0));
-                        } else {
-                            buffer.set_pixel(
-                                col + tile_x,
-                                row + (tile_y * 8),
-                                (255, colour, colour, colour),
-                            );
-                        }
+    const WHITE: u16 = 0x30;
+    const BLACK: u16 = 0x0F;
+    const GREEN: u16 = 0x2A;
+    const BLUE: u16 = 0x12;
+
+    let mut background = vec![BLACK; SCREEN_WIDTH * SCREEN_HEIGHT];
+    for tile_y in 0..30 {
+        for tile in 0..32 {
+            let pixels = patterns[(tile % 10) + 1].data;
+            for row in 0..8 {
+                for col in 0..8 {
+                    if pixels[row][col] != 0 {
+                        background[(tile_y * 8 + row) * SCREEN_WIDTH + tile * 8 + col] = WHITE;
                     }
                 }
-                tile_x += 8;
             }
         }
-    });
-
-    screen.update_sprites(|buffer| {
-        for y in 0..8 {
-            for x in 0..8 {
-                let colour = sprite[y][x] * 255;
-                if colour == 0 {
-                    buffer.set_pixel(x, y, (0, 0, 0, 0));
-                } else {
-                    buffer.set_pixel(x, y, (255, 0, colour, 0));
+    }
+    let draw_sprite = |frame: &mut Vec<u16>, x: usize, y: usize| {
+        for row in 0..8 {
+            for col in 0..8 {
+                if sprite[row][col] != 0 && x + col < SCREEN_WIDTH {
+                    frame[(y + row) * SCREEN_WIDTH + x + col] = GREEN;
                 }
             }
         }
-    });
-    use nes::ppu::screen::Rectangle;
-    screen.set_backdrop_color((0, 0, 0));
-    screen.render(
-        Rectangle {
-            x: 0,
-            y: 0,
-            width: 256,
-            height: 240,
-        },
-        0,
-        0,
-    );
-    screen.render_sprite(
-        Rectangle {
-            x: 0,
-            y: 0,
-            width: 8,
-            height: 8,
-        },
-        16,
-        8,
-        false,
-        false,
-    );
-    screen.present();
+    };
+
+    let palette = ColourPalette::default();
+    let mut frame = background.clone();
+    draw_sprite(&mut frame, 16, 8);
+    screen.present(&Frame::new(&frame, &palette));
 
     let sdl_events = sdl.event_pump();
     let start = Instant::now();
     let mut sprite_x = 0;
     for i in 0..200 {
-        let u = i as u32;
-        screen.update_buffer(|buffer| {
-            let pixels = patterns[(u as usize % 10) + 1].data;
-            for tile in 0..32 {
-                for row in 0..8 {
-                    for col in 0..8 {
-                        let colour = pixels[row][col] * 255;
-                        if colour == 0 {
-                            buffer.set_pixel(col + (tile * 8), row + (17 * 8), (0, 0, 0, 0));
-                        } else {
-                            buffer.set_pixel(
-                                col + (tile * 8),
-                                row + (17 * 8),
-                                (255, colour, colour, colour),
-                            );
-                        }
-                    }
+        let pixels = patterns[(i % 10) + 1].data;
+        for tile in 0..32 {
+            for row in 0..8 {
+                for col in 0..8 {
+                    let colour = if pixels[row][col] != 0 { WHITE } else { BLUE };
+                    background[(17 * 8 + row) * SCREEN_WIDTH + tile * 8 + col] = colour;
                 }
             }
-        });
-        screen.set_backdrop_color((0, 0, 255));
-        screen.render(
-            Rectangle {
-                x: i,
-                y: 0,
-                width: (256 - u),
-                height: 120,
-            },
-            0,
-            0,
-        );
-        screen.render(
-            Rectangle {
-                x: 0,
-                y: 0,
-                width: u,
-                height: 120,
-            },
-            256 - i as usize,
-            0,
-        );
-        screen.render(
-            Rectangle {
-                x: 0,
-                y: 120,
-                width: 256,
-                height: 240,
-            },
-            0,
-            120,
-        );
+        }
+        //Scroll the top half to the left, wrapping around
+        for y in 0..120 {
+            for x in 0..SCREEN_WIDTH {
+                frame[y * SCREEN_WIDTH + x] = background[y * SCREEN_WIDTH + (x + i) % SCREEN_WIDTH];
+            }
+        }
+        frame[120 * SCREEN_WIDTH..].copy_from_slice(&background[120 * SCREEN_WIDTH..]);
 
-        screen.render_sprite(
-            Rectangle {
-                x: 0,
-                y: 0,
-                width: 8,
-                height: 8,
-            },
-            sprite_x,
-            8,
-            false,
-            false,
-        );
+        draw_sprite(&mut frame, sprite_x, 8);
         sprite_x += 1;
-        screen.present();
+        screen.present(&Frame::new(&frame, &palette));
         std::thread::sleep(Duration::from_millis(50));
 
         if sdl_events.load().a_button {
@@ -8,8 +8,9 @@ use nes::Clock;
 pub fn start() {
     let sdl = SDL2::new();
     let audio = sdl.audio();
-    let mut apu = APU::new(audio, 100);
+    let mut apu = APU::new(audio, 0.5);
     let square1 = apu.square1();
+    square1.borrow_mut().set_enabled(true);
     let mut cpu_memory = cpu_memory!(
         box BasicMemory::new(),
         0x4000 => MutableRef::Box(box Register1(square1.clone())),
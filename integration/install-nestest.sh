@@ -0,0 +1,13 @@
+#!/bin/bash
+
+if [ -f .nestest/nestest.nes ] && [ -f .nestest/nestest.log ]
+then
+    echo "nestest already installed"
+else
+    echo "Installing nestest"
+    rm -rf .nestest
+    mkdir .nestest
+    curl -sSfL -o .nestest/nestest.nes https://www.qmtpro.com/~nes/misc/nestest.nes &&
+    curl -sSfL -o .nestest/nestest.log https://www.qmtpro.com/~nes/misc/nestest.log || exit 1
+    echo "Installed nestest in .nestest/"
+fi
@@ -0,0 +1,32 @@
+extern crate integration;
+
+use integration::suite;
+
+use std::env;
+use std::panic;
+use std::path::Path;
+use std::process::exit;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        panic!("Usage: {} DIRECTORY", args[0]);
+    }
+
+    //Crashing ROMs are reported in the matrix
+    panic::set_hook(Box::new(|_| {}));
+    let results = suite::run_directory(Path::new(&args[1]));
+    let _ = panic::take_hook();
+    let results = match results {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(2);
+        }
+    };
+
+    suite::print_matrix(&results);
+    if results.iter().any(|r| !r.passed()) {
+        exit(1);
+    }
+}
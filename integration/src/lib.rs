@@ -3,3 +3,4 @@ extern crate nes;
 pub mod audio_device;
 pub mod controller;
 pub mod rom_test;
+pub mod suite;
@@ -4,12 +4,23 @@ use audio_device;
 use controller;
 
 use nes::input::standard_controller::StandardController;
-use nes::memory::Memory;
-use nes::ppu::screen::ScreenMock;
+use nes::ppu::screen::{Screen, ScreenMock};
 use nes::NES;
 
 use nes::borrow::MutableRef;
 
+const TIMEOUT_CYCLES: u64 = 10_000_000;
+const RESET_DELAY_FRAMES: u64 = 6;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    Passed,
+    Failed(u8),
+    ChecksumMismatch(u32),
+    TimedOut,
+    Crashed(String),
+}
+
 pub fn test(rom_file: &str) {
     let controller = controller::FakeController::new();
     let standard_controller = StandardController::new(&controller);
@@ -22,26 +33,12 @@ pub fn test(rom_file: &str) {
         screen,
     );
 
-    while nes.memory.get(0x6000, 0) == 0 && nes.cycle_count < 10000000 {
-        nes.execute();
-    }
-    println!("Test started");
-    while nes.memory.get(0x6000, 0) == 0x80 && nes.cycle_count < 10000000 {
-        nes.execute();
-    }
-
-    if nes.cycle_count >= 10000000 {
-        panic!("{} Timed out!", rom_file);
-    }
-
-    for _ in 0..29781 {
-        nes.execute();
-    }
-
-    match nes.memory.get(0x6000, 0) {
-        0x00 => {}
-        code => {
+    match run(&mut nes, TIMEOUT_CYCLES) {
+        (Outcome::Passed, _) => {}
+        (Outcome::TimedOut, _) => panic!("{} Timed out!", rom_file),
+        (outcome, text) => {
             println!("{}", rom_file);
+            println!("{}", text);
             let base_address = 0x2000;
             for row in 0..30 {
                 for col in 0..32 {
@@ -54,7 +51,153 @@ pub fn test(rom_file: &str) {
                 }
                 println!("");
             }
-            panic!("Failed with code {:x}", code);
+            panic!("Failed with {:?}", outcome);
+        }
+    }
+}
+
+/**
+ * Runs a test ROM until it reports its result in $6000: $80 while running,
+ * $81 to ask for the reset button to be pressed, and below $80 the result code
+ * with 0 meaning passed. ROMs writing the signature DE B0 61 to $6001-$6003
+ * also leave a text at $6004, which is returned with the outcome.
+ */
+pub fn run<S, A>(nes: &mut NES<S, A>, timeout_cycles: u64) -> (Outcome, String)
+where
+    S: Screen + Sized,
+    A: nes::sound::AudioDevice + Sized,
+{
+    let end = nes.cycle_count + timeout_cycles;
+    let mut started = false;
+    while nes.cycle_count < end {
+        nes.execute();
+        match nes.memory.peek(0x6000) {
+            0x80 => started = true,
+            0x81 if started => {
+                //The reset button must be held for at least 100ms
+                run_frames(nes, RESET_DELAY_FRAMES);
+                nes.reset();
+                started = false;
+            }
+            code if started && code < 0x80 => {
+                //Gives the ROM a frame to finish writing its result
+                run_frames(nes, 1);
+                let code = nes.memory.peek(0x6000);
+                let outcome = if code == 0 {
+                    Outcome::Passed
+                } else {
+                    Outcome::Failed(code)
+                };
+                return (outcome, result_text(nes));
+            }
+            _ => {}
         }
     }
+    (Outcome::TimedOut, result_text(nes))
+}
+
+pub fn cpu_cycles_per_frame<S, A>(nes: &NES<S, A>) -> u64
+where
+    S: Screen + Sized,
+    A: nes::sound::AudioDevice + Sized,
+{
+    nes.ppu.borrow().region().cpu_cycles_per_frame() as u64
+}
+
+fn has_signature<S, A>(nes: &NES<S, A>) -> bool
+where
+    S: Screen + Sized,
+    A: nes::sound::AudioDevice + Sized,
+{
+    nes.memory.peek(0x6001) == 0xDE
+        && nes.memory.peek(0x6002) == 0xB0
+        && nes.memory.peek(0x6003) == 0x61
+}
+
+fn result_text<S, A>(nes: &NES<S, A>) -> String
+where
+    S: Screen + Sized,
+    A: nes::sound::AudioDevice + Sized,
+{
+    if !has_signature(nes) {
+        return String::new();
+    }
+    (0x6004..0x8000)
+        .map(|address| nes.memory.peek(address))
+        .take_while(|&c| c != 0)
+        .map(|c| c as char)
+        .collect()
+}
+
+pub fn run_frames<S, A>(nes: &mut NES<S, A>, frames: u64)
+where
+    S: Screen + Sized,
+    A: nes::sound::AudioDevice + Sized,
+{
+    let end = nes.cycle_count + frames * cpu_cycles_per_frame(nes);
+    while nes.cycle_count < end {
+        nes.execute();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{run, Outcome};
+    use audio_device;
+    use controller::FakeController;
+    use nes::borrow::MutableRef;
+    use nes::cpu::opcodes;
+    use nes::ines::mapper::Mapper;
+    use nes::input::standard_controller::StandardController;
+    use nes::memory::{BasicMemory, Memory};
+    use nes::ppu::ppumemory::PPUMemory;
+    use nes::ppu::screen::ScreenMock;
+    use nes::region::Region;
+    use nes::NES;
+
+    /**
+     * Runs a program at $8000 writing `results` to $6000 one after another.
+     */
+    fn run_writing(results: &[u8]) -> (Outcome, String) {
+        let mut memory = BasicMemory::new();
+        let mut address = 0x8000;
+        for &result in results.iter() {
+            let write = [
+                opcodes::LDA_IMMEDIATE,
+                result,
+                opcodes::STA_ABSOLUTE,
+                0x00,
+                0x60,
+            ];
+            for &byte in write.iter() {
+                memory.set(address, byte, 0);
+                address += 1;
+            }
+        }
+        memory.set(address, opcodes::JMP_ABSOLUTE, 0);
+        memory.set(address + 1, address as u8, 0);
+        memory.set(address + 2, (address >> 8) as u8, 0);
+        memory.set(0xFFFC, 0x00, 0);
+        memory.set(0xFFFD, 0x80, 0);
+
+        let controller = FakeController::new();
+        let mut nes = NES::new(
+            Mapper {
+                cpu_memory: box memory,
+                ppu_memory: PPUMemory::no_mirroring(),
+                region: Region::NTSC,
+            },
+            MutableRef::Box(box StandardController::new(&controller)),
+            audio_device::AudioDevice {},
+            box ScreenMock::new(),
+        );
+        run(&mut nes, 100_000)
+    }
+
+    #[test]
+    fn result_should_only_count_once_the_test_has_started() {
+        assert_eq!(Outcome::Passed, run_writing(&[0x00, 0x80, 0x00]).0);
+        assert_eq!(Outcome::Failed(3), run_writing(&[0x03, 0x80, 0x03]).0);
+        assert_eq!(Outcome::TimedOut, run_writing(&[0x00]).0);
+    }
 }
@@ -0,0 +1,327 @@
+extern crate nes;
+
+use audio_device;
+use controller;
+use rom_test;
+use rom_test::{cpu_cycles_per_frame, run_frames};
+
+use nes::borrow::MutableRef;
+use nes::input::standard_controller::StandardController;
+use nes::ppu::screen::ScreenMock;
+use nes::NES;
+
+use std::fs::{read_dir, File};
+use std::io::{BufRead, BufReader};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+pub use rom_test::Outcome;
+
+const DEFAULT_TIMEOUT_FRAMES: u64 = 60 * 120;
+const DEFAULT_SCREEN_FRAMES: u64 = 60 * 10;
+const CHECKSUM_FILE: &'static str = "checksums.txt";
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Expectation {
+    /**
+     * The ROM reports its result through the $6000 status byte and the text at $6004.
+     */
+    Protocol,
+    /**
+     * The ROM only reports on screen. The CRC-32 of the rendered frame after
+     * the given number of frames must match.
+     */
+    ScreenChecksum { crc: u32, frames: u64 },
+}
+
+pub struct TestResult {
+    pub suite: String,
+    pub rom: String,
+    pub outcome: Outcome,
+    pub text: String,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.outcome == Outcome::Passed
+    }
+}
+
+/**
+ * Runs every .nes file below the given directory. Each sub directory is reported
+ * as a suite and may contain a checksums.txt with lines of the form
+ * `rom.nes CRC32 [frames]` for ROMs that only report their result on screen.
+ */
+pub fn run_directory(directory: &Path) -> Result<Vec<TestResult>, String> {
+    let mut results = vec![];
+    for suite in suites(directory) {
+        let expectations = read_checksums(&suite.join(CHECKSUM_FILE))?;
+        let suite_name = suite
+            .strip_prefix(directory)
+            .ok()
+            .and_then(|p| p.to_str())
+            .filter(|p| !p.is_empty())
+            .unwrap_or(".")
+            .to_string();
+        for rom in roms(&suite) {
+            let file_name = rom.file_name().unwrap().to_string_lossy().to_string();
+            let expectation = expectations
+                .iter()
+                .find(|e| e.0 == file_name)
+                .map(|e| e.1.clone())
+                .unwrap_or(Expectation::Protocol);
+            let (outcome, text) = run_rom(&rom, &expectation);
+            results.push(TestResult {
+                suite: suite_name.clone(),
+                rom: file_name,
+                outcome: outcome,
+                text: text,
+            });
+        }
+    }
+    Ok(results)
+}
+
+pub fn run_rom(rom: &Path, expectation: &Expectation) -> (Outcome, String) {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let controller = controller::FakeController::new();
+        let standard_controller = StandardController::new(&controller);
+        let mut nes = NES::from_file(
+            rom.to_str().unwrap(),
+            MutableRef::Box(box standard_controller),
+            audio_device::AudioDevice {},
+            box ScreenMock::new(),
+        );
+        match *expectation {
+            Expectation::Protocol => {
+                let timeout = DEFAULT_TIMEOUT_FRAMES * cpu_cycles_per_frame(&nes);
+                rom_test::run(&mut nes, timeout)
+            }
+            Expectation::ScreenChecksum { crc, frames } => {
+                run_frames(&mut nes, frames);
+                let actual = screen_checksum(&nes);
+                if actual == crc {
+                    (Outcome::Passed, String::new())
+                } else {
+                    (Outcome::ChecksumMismatch(actual), String::new())
+                }
+            }
+        }
+    }));
+    match result {
+        Ok(result) => result,
+        Err(cause) => {
+            let message = cause
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| cause.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or(String::from("unknown panic"));
+            (Outcome::Crashed(message), String::new())
+        }
+    }
+}
+
+/**
+ * CRC-32 of the palette indices of the last rendered frame, little endian,
+ * so that the checksums do not depend on the colour palette.
+ */
+pub fn screen_checksum(nes: &NES<ScreenMock, audio_device::AudioDevice>) -> u32 {
+    let ppu = nes.ppu.borrow();
+    let frame: Vec<u8> = ppu
+        .frame()
+        .pixels()
+        .iter()
+        .flat_map(|&pixel| vec![pixel as u8, (pixel >> 8) as u8])
+        .collect();
+    crc32(&frame)
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn suites(directory: &Path) -> Vec<PathBuf> {
+    let mut suites = vec![directory.to_path_buf()];
+    let mut index = 0;
+    while index < suites.len() {
+        let mut sub_directories: Vec<PathBuf> = read_dir(&suites[index])
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_dir())
+            .collect();
+        sub_directories.sort();
+        suites.extend(sub_directories);
+        index += 1;
+    }
+    suites
+}
+
+fn roms(directory: &Path) -> Vec<PathBuf> {
+    let mut roms: Vec<PathBuf> = read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|e| e == "nes").unwrap_or(false))
+        .collect();
+    roms.sort();
+    roms
+}
+
+fn read_checksums(file: &Path) -> Result<Vec<(String, Expectation)>, String> {
+    let reader = match File::open(file) {
+        Ok(file) => BufReader::new(file),
+        Err(_) => return Ok(vec![]),
+    };
+    let mut expectations = vec![];
+    for (number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| format!("{}: {}", file.display(), e))?;
+        let expectation = parse_checksum(&line)
+            .map_err(|e| format!("{} line {}: {}", file.display(), number + 1, e))?;
+        expectations.extend(expectation);
+    }
+    Ok(expectations)
+}
+
+fn parse_checksum(line: &str) -> Result<Option<(String, Expectation)>, String> {
+    let line = line.split('#').next().unwrap().trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let columns: Vec<&str> = line.split_whitespace().collect();
+    if columns.len() < 2 || columns.len() > 3 {
+        return Err(format!("Invalid checksum line '{}'", line));
+    }
+    let crc = u32::from_str_radix(columns[1], 16)
+        .map_err(|_| format!("Invalid checksum '{}'", columns[1]))?;
+    let frames = match columns.get(2) {
+        Some(f) => f
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid frame count '{}'", f))?,
+        None => DEFAULT_SCREEN_FRAMES,
+    };
+    Ok(Some((
+        columns[0].to_string(),
+        Expectation::ScreenChecksum {
+            crc: crc,
+            frames: frames,
+        },
+    )))
+}
+
+/**
+ * One row per ROM with one column per outcome, followed by a summary per suite.
+ */
+pub fn print_matrix(results: &[TestResult]) {
+    let width = results.iter().map(|r| r.rom.len()).max().unwrap_or(0);
+    let mut suites: Vec<&str> = vec![];
+    for result in results {
+        if !suites.contains(&result.suite.as_str()) {
+            suites.push(&result.suite);
+        }
+    }
+    for suite in suites.iter() {
+        println!("{}", suite);
+        for result in results.iter().filter(|r| r.suite == *suite) {
+            let outcome = match result.outcome {
+                Outcome::Passed => String::from("PASS"),
+                Outcome::Failed(code) => format!("FAIL ({})", code),
+                Outcome::ChecksumMismatch(crc) => format!("FAIL (checksum {:08X})", crc),
+                Outcome::TimedOut => String::from("TIMEOUT"),
+                Outcome::Crashed(ref message) => format!("CRASH ({})", message),
+            };
+            println!("  {:<width$}  {}", result.rom, outcome, width = width);
+            if !result.passed() {
+                for line in result.text.lines().filter(|l| !l.trim().is_empty()) {
+                    println!("  {:<width$}    {}", "", line, width = width);
+                }
+            }
+        }
+    }
+    println!("");
+    for suite in suites.iter() {
+        let total = results.iter().filter(|r| r.suite == *suite).count();
+        let passed = results
+            .iter()
+            .filter(|r| r.suite == *suite && r.passed())
+            .count();
+        println!("{}: {}/{} passed", suite, passed, total);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc32, parse_checksum, read_checksums, Expectation, DEFAULT_SCREEN_FRAMES};
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn crc32_of_check_string() {
+        assert_eq!(0xCBF43926, crc32(b"123456789"));
+    }
+
+    #[test]
+    fn checksum_lines() {
+        assert_eq!(Ok(None), parse_checksum("# sprite_hit_tests"));
+        assert_eq!(
+            Ok(Some((
+                String::from("01.basics.nes"),
+                Expectation::ScreenChecksum {
+                    crc: 0x1234ABCD,
+                    frames: DEFAULT_SCREEN_FRAMES,
+                }
+            ))),
+            parse_checksum("01.basics.nes 1234abcd")
+        );
+        assert_eq!(
+            Ok(Some((
+                String::from("02.alignment.nes"),
+                Expectation::ScreenChecksum {
+                    crc: 0xCAFE,
+                    frames: 120,
+                }
+            ))),
+            parse_checksum("02.alignment.nes CAFE 120 # two seconds")
+        );
+    }
+
+    #[test]
+    fn malformed_checksum_lines_should_be_errors() {
+        assert_eq!(
+            Err(String::from("Invalid checksum line '01.basics.nes'")),
+            parse_checksum("01.basics.nes")
+        );
+        assert_eq!(
+            Err(String::from("Invalid checksum 'xyz'")),
+            parse_checksum("01.basics.nes xyz")
+        );
+        assert_eq!(
+            Err(String::from("Invalid frame count 'ten'")),
+            parse_checksum("01.basics.nes CAFE ten")
+        );
+    }
+
+    #[test]
+    fn checksum_file_errors_should_name_the_line() {
+        let file = env::temp_dir().join("rustiness-checksums-test.txt");
+        fs::write(&file, "# roms\n01.basics.nes CAFE\n02.alignment.nes\n").unwrap();
+        let result = read_checksums(&file);
+        fs::remove_file(&file).unwrap();
+        assert_eq!(
+            Err(format!(
+                "{} line 3: Invalid checksum line '02.alignment.nes'",
+                file.display()
+            )),
+            result
+        );
+    }
+}
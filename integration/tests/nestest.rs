@@ -0,0 +1,80 @@
+#![feature(box_syntax)]
+extern crate integration;
+extern crate nes;
+
+use integration::{audio_device, controller};
+use nes::borrow::MutableRef;
+use nes::cpu::CPU;
+use nes::input::standard_controller::StandardController;
+use nes::memory::Memory;
+use nes::ppu::screen::ScreenMock;
+use nes::NES;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+const ROM: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/.nestest/nestest.nes");
+const LOG: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/.nestest/nestest.log");
+/**
+ * Lines of the log before the first unofficial opcode, `*NOP` at $C6BD.
+ */
+const OFFICIAL_OPCODE_LINES: usize = 5003;
+
+/**
+ * Compares the trace with the nestest log line by line. Only the official
+ * opcodes are emulated, so this stops at the first unofficial one, marked
+ * with a `*` in the log, after all the tests of the official opcodes. The rest
+ * of the log only tests unofficial opcodes.
+ *
+ * The ROM and the log are not part of the repository, they are downloaded
+ * with install-nestest.sh.
+ */
+#[test]
+fn nestest() {
+    assert!(
+        Path::new(ROM).exists() && Path::new(LOG).exists(),
+        "nestest is missing, run install-nestest.sh to download it"
+    );
+    let controller = controller::FakeController::new();
+    let standard_controller = StandardController::new(&controller);
+
+    let mut nes = NES::from_file(
+        ROM,
+        MutableRef::Box(box standard_controller),
+        audio_device::AudioDevice {},
+        box ScreenMock::new(),
+    );
+
+    //Automation mode, i.e. start at $C000 in the state the log expects after reset
+    nes.cpu = CPU::new(0xC000);
+    nes.cpu.stack_pointer = 0xFD;
+    nes.cpu.set_processor_status(0x24);
+    nes.ppu.borrow_mut().sync(7, &mut ScreenMock::new());
+    nes.cycle_count = 7;
+
+    let log = BufReader::new(File::open(LOG).unwrap());
+    let mut compared_lines = 0;
+    for (line, expected) in log.lines().enumerate() {
+        let expected = expected.unwrap();
+        let expected = expected.trim_end();
+        if expected.as_bytes()[15] == b'*' {
+            assert!(expected.starts_with("C6BD"), "{}", expected);
+            break;
+        }
+        let actual = nes::trace::trace(&nes);
+        if actual != expected {
+            panic!(
+                "nestest diverged at line {}\nexpected: {}\nactual:   {}",
+                line + 1,
+                expected,
+                actual
+            );
+        }
+        nes.execute();
+        compared_lines += 1;
+    }
+    assert_eq!(OFFICIAL_OPCODE_LINES, compared_lines);
+    //nestest keeps the code of the first failed official opcode test in $02
+    assert_eq!(0x00, nes.memory.get(0x0002, 0));
+}
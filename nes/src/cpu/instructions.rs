@@ -419,13 +419,61 @@ impl NMI {
 }
 impl Instruction for NMI {
     fn execute(&self, cpu: &mut CPU, memory: &mut dyn Memory) -> u8 {
-        let current_pc = cpu.program_counter();
-        memory.set(cpu.push_stack(), (current_pc >> 8) as u8, 2);
-        memory.set(cpu.push_stack(), current_pc as u8, 3);
-        memory.set(cpu.push_stack(), cpu.processor_status() | 0x20, 4);
+        interrupt(cpu, memory, 0xFFFA);
+        return self.estimated_cycles();
+    }
+    fn estimated_cycles(&self) -> u8 {
+        return 7;
+    }
+}
+
+pub struct IRQ;
+impl IRQ {
+    pub fn new() -> IRQ {
+        IRQ
+    }
+}
+impl Instruction for IRQ {
+    fn execute(&self, cpu: &mut CPU, memory: &mut dyn Memory) -> u8 {
+        interrupt(cpu, memory, 0xFFFE);
+        return self.estimated_cycles();
+    }
+    fn estimated_cycles(&self) -> u8 {
+        return 7;
+    }
+}
+
+/**
+ * Pushes the program counter and the status, without the B flag, and jumps
+ * through the vector at `vector`.
+ */
+fn interrupt(cpu: &mut CPU, memory: &mut dyn Memory, vector: u16) {
+    let current_pc = cpu.program_counter();
+    memory.set(cpu.push_stack(), (current_pc >> 8) as u8, 2);
+    memory.set(cpu.push_stack(), current_pc as u8, 3);
+    memory.set(cpu.push_stack(), cpu.processor_status() | 0x20, 4);
+
+    let lsbs: u8 = memory.get(vector, 5);
+    let msbs: u8 = memory.get(vector + 1, 6);
+    cpu.set_program_counter((msbs as u16) << 8 | lsbs as u16);
+    cpu.set_flags(cpu::INTERRUPT_DISABLE_FLAG);
+}
+
+pub struct RESET;
+impl RESET {
+    pub fn new() -> RESET {
+        RESET
+    }
+}
+impl Instruction for RESET {
+    fn execute(&self, cpu: &mut CPU, memory: &mut dyn Memory) -> u8 {
+        //The stack pointer is decremented as for an interrupt but the writes are turned into reads
+        memory.get(cpu.push_stack(), 2);
+        memory.get(cpu.push_stack(), 3);
+        memory.get(cpu.push_stack(), 4);
 
-        let lsbs: u8 = memory.get(0xFFFA, 5);
-        let msbs: u8 = memory.get(0xFFFB, 6);
+        let lsbs: u8 = memory.get(0xFFFC, 5);
+        let msbs: u8 = memory.get(0xFFFD, 6);
         cpu.set_program_counter((msbs as u16) << 8 | lsbs as u16);
         cpu.set_flags(cpu::INTERRUPT_DISABLE_FLAG);
         return self.estimated_cycles();
@@ -1,5 +1,6 @@
 use memory::Memory;
 use ppu::ppumemory::{Mirroring, PPUMemory};
+use region::Region;
 use std::fs::File;
 use std::io::Read;
 
@@ -10,6 +11,7 @@ pub struct INes {
     pub num_prg_roms: u8,
     pub num_chr_roms: u8,
     pub mirroring: Mirroring,
+    pub region: Region,
 }
 
 impl<'a> INes {
@@ -23,11 +25,13 @@ impl<'a> INes {
         } else {
             Mirroring::Vertical
         };
+        let region = Region::from_header(&buffer[0..0x10]);
         INes {
             buffer: buffer,
             num_prg_roms: num_prg_roms,
             num_chr_roms: num_chr_roms,
             mirroring: mirroring,
+            region: region,
         }
     }
 
@@ -41,11 +45,13 @@ impl<'a> INes {
         } else {
             Mirroring::Vertical
         };
+        let region = Region::from_header(&buffer[0..0x10]);
         INes {
             buffer: buffer,
             num_prg_roms: num_prg_roms,
             num_chr_roms: num_chr_roms,
             mirroring: mirroring,
+            region: region,
         }
     }
 
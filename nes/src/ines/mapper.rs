@@ -1,11 +1,53 @@
 use ines::INes;
-use memory::{BasicMemory, Memory};
+use memory::{Address, Memory};
 use ppu::ppumemory::PPUMemory;
+use region::Region;
 use std::fs::File;
 
 pub struct Mapper {
     pub cpu_memory: Box<dyn Memory>,
     pub ppu_memory: PPUMemory,
+    /**
+     * The region the game was made for according to its header.
+     */
+    pub region: Region,
+}
+
+/**
+ * Mapper 0. 8K of PRG RAM at $6000-$7FFF and one or two 16K PRG ROM banks
+ * at $8000-$FFFF. A single bank is mirrored at $C000.
+ */
+pub struct NROM {
+    prg_ram: Vec<u8>,
+    prg_rom: Vec<u8>,
+}
+
+impl NROM {
+    pub fn new(prg_rom: Vec<u8>) -> NROM {
+        NROM {
+            prg_ram: vec![0; 0x2000],
+            prg_rom: prg_rom,
+        }
+    }
+}
+
+impl Memory for NROM {
+    fn get(&self, address: Address, _: u8) -> u8 {
+        if address >= 0x8000 {
+            self.prg_rom[(address as usize - 0x8000) % self.prg_rom.len()]
+        } else if address >= 0x6000 {
+            self.prg_ram[address as usize - 0x6000]
+        } else {
+            0
+        }
+    }
+
+    fn set(&mut self, address: Address, value: u8, _: u8) {
+        //Writes to ROM are ignored
+        if address >= 0x6000 && address < 0x8000 {
+            self.prg_ram[address as usize - 0x6000] = value;
+        }
+    }
 }
 
 pub fn from_file(file_name: &str) -> Mapper {
@@ -13,16 +55,13 @@ pub fn from_file(file_name: &str) -> Mapper {
 
     Mapper {
         cpu_memory: {
-            let mut cpu_memory = box BasicMemory::new();
-            cpu_memory.set_slice(0x8000, ines.prg_rom(0));
-            if ines.num_prg_roms == 1 {
-                cpu_memory.set_slice(0xC000, ines.prg_rom(0));
-            } else if ines.num_prg_roms == 2 {
-                cpu_memory.set_slice(0xC000, ines.prg_rom(1));
-            } else {
+            let mut prg_rom = ines.prg_rom(0).to_vec();
+            if ines.num_prg_roms == 2 {
+                prg_rom.extend_from_slice(ines.prg_rom(1));
+            } else if ines.num_prg_roms > 2 {
                 panic!(".nes file contains more than 2 prg rom banks which is not allowed");
             }
-            cpu_memory
+            box NROM::new(prg_rom)
         },
         ppu_memory: {
             let mut ppu_mem = PPUMemory::new(ines.mirroring);
@@ -31,5 +70,39 @@ pub fn from_file(file_name: &str) -> Mapper {
             }
             ppu_mem
         },
+        region: ines.region,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NROM;
+    use memory::Memory;
+
+    #[test]
+    fn prg_rom_should_be_read_only() {
+        let mut nrom = NROM::new(vec![0x42; 0x4000]);
+        nrom.set(0x8000, 0x01, 0);
+        nrom.set(0xFFFF, 0x01, 0);
+        assert_eq!(0x42, nrom.get(0x8000, 0));
+        assert_eq!(0x42, nrom.get(0xFFFF, 0));
+    }
+
+    #[test]
+    fn a_single_prg_rom_bank_should_be_mirrored() {
+        let mut prg_rom = vec![0; 0x4000];
+        prg_rom[0x0010] = 0x17;
+        let nrom = NROM::new(prg_rom);
+        assert_eq!(0x17, nrom.get(0x8010, 0));
+        assert_eq!(0x17, nrom.get(0xC010, 0));
+    }
+
+    #[test]
+    fn prg_ram_should_be_writable() {
+        let mut nrom = NROM::new(vec![0; 0x8000]);
+        nrom.set(0x6000, 0x80, 0);
+        nrom.set(0x7FFF, 0x81, 0);
+        assert_eq!(0x80, nrom.get(0x6000, 0));
+        assert_eq!(0x81, nrom.get(0x7FFF, 0));
     }
 }
@@ -84,6 +84,10 @@ impl<'a> MemoryMappedIO for StandardController<'a> {
         }
         self.state_loaded = if strobe_bit == 0 { true } else { false };
     }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xE0
+    }
 }
 
 #[cfg(test)]
@@ -6,21 +6,67 @@ pub mod cpu;
 pub mod ines;
 pub mod input;
 pub mod ppu;
+pub mod region;
 pub mod sound;
+pub mod trace;
 
 use cpu::CPU;
-use memory::{CPUMemory, Memory};
-use ppu::screen::Screen;
+use memory::{CPUMemory, Memory, RamPattern};
+use ppu::events::EventKind;
+use ppu::screen::{ColourPalette, Screen};
 use ppu::PPU;
+use region::Region;
 
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use sound::registers::{Register1, Register3, Register4};
+use sound::dmc;
+use sound::registers::{
+    DMCRegister1, DMCRegister2, DMCRegister3, DMCRegister4, FrameCounterRegister, NoiseRegister1,
+    NoiseRegister3, NoiseRegister4, Register1, Register2, Register3, Register4, StatusRegister,
+    TriangleRegister1, TriangleRegister3, TriangleRegister4,
+};
 use sound::AudioDevice;
 use sound::APU;
 
-const NANOS_PER_CLOCK_CYCLE: u32 = 559;
+/**
+ * Deviations from the original hardware, chosen when the NES is created.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Settings {
+    /**
+     * Only draw 8 sprites per scanline. Turning this off removes the flickering
+     * in games with many sprites.
+     */
+    pub sprite_limit: bool,
+    /**
+     * Colours used for the 512 palette entries (64 colours times 8 emphasis
+     * combinations). The same palette is used in every region, PAL and Dendy
+     * only differ in the order of the emphasis bits.
+     */
+    pub palette: ColourPalette,
+    /**
+     * Runs the game with the timing of this region instead of the one in its header.
+     */
+    pub region: Option<Region>,
+    /**
+     * Lets the audio queue set the speed instead of the wall clock, keeping
+     * about this many milliseconds of audio queued. Only used with the sound
+     * feature and an audio device that reports its queue.
+     */
+    pub audio_pacing: Option<u32>,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            sprite_limit: true,
+            palette: ColourPalette::default(),
+            region: None,
+            audio_pacing: None,
+        }
+    }
+}
 
 pub struct NES<'a, T, A>
 where
@@ -36,6 +82,7 @@ where
     pub memory: CPUMemory<'a>,
 
     pub clock: Clock,
+    log_events: bool,
 }
 
 use borrow::MutableRef;
@@ -65,12 +112,30 @@ where
         controller: MutableRef<'a, dyn MemoryMappedIO>,
         audio: A,
         screen: Box<T>,
+    ) -> NES<'a, T, A> {
+        NES::with_settings(mapper, controller, audio, screen, Settings::default())
+    }
+
+    pub fn with_settings(
+        mapper: mapper::Mapper,
+        controller: MutableRef<'a, dyn MemoryMappedIO>,
+        audio: A,
+        screen: Box<T>,
+        settings: Settings,
     ) -> NES<'a, T, A> {
         let memory = mapper.cpu_memory;
+        let region = settings.region.unwrap_or(mapper.region);
 
         let ppu = Rc::new(RefCell::new(PPU::new(mapper.ppu_memory)));
+        ppu.borrow_mut().set_sprite_limit(settings.sprite_limit);
+        ppu.borrow_mut().set_palette(settings.palette);
+        ppu.borrow_mut().set_region(region);
 
-        let apu = APU::new(audio, 500);
+        let mut apu = APU::new(audio, 0.5);
+        apu.set_region(region);
+        if cfg!(feature = "sound") {
+            apu.set_audio_pacing(settings.audio_pacing);
+        }
 
         let cpu_start = {
             let lsbs: u8 = memory.get(0xFFFC, 0);
@@ -86,14 +151,44 @@ where
             op_codes: opcodes::OpCodes::new(),
             screen: screen,
             memory: cpu_memory,
-            clock: Clock::start(),
+            clock: Clock::for_region(region),
+            log_events: false,
         }
     }
 
     pub fn execute(&mut self) {
+        if self.log_events {
+            self.ppu
+                .borrow_mut()
+                .set_program_counter(self.cpu.program_counter());
+        }
         let cycles = self
             .op_codes
             .execute_instruction(&mut self.cpu, &mut self.memory);
+        let mut nmi = self.run_cycles(cycles);
+
+        let sample_address = self.apu.dmc().borrow().sample_address();
+        if let Some(address) = sample_address {
+            let sample = self.memory.get(address, 0);
+            self.apu.dmc().borrow_mut().load_sample(sample);
+            nmi = self.run_cycles(dmc::DMA_CYCLES) || nmi;
+        }
+
+        if !nmi && self.apu.irq() && !self.cpu.is_flag_set(cpu::INTERRUPT_DISABLE_FLAG) {
+            self.ppu.borrow_mut().log_event(EventKind::Irq, 0);
+            nmi = self.interrupt(instructions::IRQ::new());
+        }
+        //An NMI signalled during the IRQ or NMI sequence is taken after it
+        while nmi {
+            nmi = self.interrupt(instructions::NMI::new());
+        }
+    }
+
+    /**
+     * Lets the PPU and APU catch up with the CPU, returns true if the PPU
+     * signalled an NMI.
+     */
+    fn run_cycles(&mut self, cycles: u8) -> bool {
         let nmi = self
             .ppu
             .borrow_mut()
@@ -101,24 +196,65 @@ where
 
         if cfg!(feature = "sound") {
             self.apu.update(cycles);
+        } else {
+            self.apu.update_irq_sources(cycles);
         }
         self.cycle_count += cycles as u64;
-        self.clock.tick(cycles as u32);
-
-        if nmi {
-            let nmi_instruction = instructions::NMI::new();
-            let cycles = nmi_instruction.estimated_cycles();
-            nmi_instruction.execute(&mut self.cpu, &mut self.memory);
-            self.ppu
-                .borrow_mut()
-                .sync(cycles as u32, self.screen.as_mut());
-            self.cycle_count += cycles as u64;
+        if self.apu.is_audio_paced() {
+            self.clock.keep_up();
+        } else {
+            self.clock.tick(cycles as u32);
         }
+        nmi
+    }
+
+    /**
+     * Runs the interrupt sequence, returns true if the PPU signalled an NMI
+     * meanwhile.
+     */
+    fn interrupt<I: Instruction>(&mut self, instruction: I) -> bool {
+        let cycles = instruction.estimated_cycles();
+        instruction.execute(&mut self.cpu, &mut self.memory);
+        self.run_cycles(cycles)
+    }
+
+    /**
+     * Equivalent of pressing the RESET button.
+     */
+    pub fn reset(&mut self) {
+        self.apu.reset();
+        self.ppu.borrow_mut().reset();
+        let reset_instruction = instructions::RESET::new();
+        let cycles = reset_instruction.execute(&mut self.cpu, &mut self.memory);
+        self.ppu
+            .borrow_mut()
+            .sync(cycles as u32, self.screen.as_mut());
+        self.cycle_count += cycles as u64;
+    }
+
+    /**
+     * Turns the console off and on again, filling the internal RAM with the given pattern.
+     */
+    pub fn power_cycle(&mut self, ram_pattern: RamPattern) {
+        ram_pattern.fill(&mut self.memory, 0x0000..0x0800);
+        self.cpu = CPU::new(0);
+        self.cpu.stack_pointer = 0;
+        self.ppu.borrow_mut().power_cycle();
+        self.reset();
+    }
+
+    /**
+     * Records PPU register writes and interrupts with the dot they happened at,
+     * see `PPU::events`.
+     */
+    pub fn set_event_logging(&mut self, enabled: bool) {
+        self.log_events = enabled;
+        self.ppu.borrow_mut().set_event_logging(enabled);
     }
 
     #[inline]
     pub fn resume(&mut self) {
-        self.clock = Clock::start();
+        self.clock = Clock::for_region(self.ppu.borrow().region());
     }
 }
 
@@ -148,11 +284,25 @@ impl<'a> CPUMemory<'a> {
             0x2007 => MutableRef::Box(box PPUData(ppu.clone())),
 
             0x4000 => MutableRef::Box(box Register1(apu.square1())),
+            0x4001 => MutableRef::Box(box Register2(apu.square1())),
             0x4002 => MutableRef::Box(box Register3(apu.square1())),
             0x4003 => MutableRef::Box(box Register4(apu.square1())),
             0x4004 => MutableRef::Box(box Register1(apu.square2())),
+            0x4005 => MutableRef::Box(box Register2(apu.square2())),
             0x4006 => MutableRef::Box(box Register3(apu.square2())),
             0x4007 => MutableRef::Box(box Register4(apu.square2())),
+            0x4008 => MutableRef::Box(box TriangleRegister1(apu.triangle())),
+            0x400A => MutableRef::Box(box TriangleRegister3(apu.triangle())),
+            0x400B => MutableRef::Box(box TriangleRegister4(apu.triangle())),
+            0x400C => MutableRef::Box(box NoiseRegister1(apu.noise())),
+            0x400E => MutableRef::Box(box NoiseRegister3(apu.noise())),
+            0x400F => MutableRef::Box(box NoiseRegister4(apu.noise())),
+            0x4010 => MutableRef::Box(box DMCRegister1(apu.dmc())),
+            0x4011 => MutableRef::Box(box DMCRegister2(apu.dmc())),
+            0x4012 => MutableRef::Box(box DMCRegister3(apu.dmc())),
+            0x4013 => MutableRef::Box(box DMCRegister4(apu.dmc())),
+            0x4015 => MutableRef::Box(box StatusRegister::new(apu)),
+            0x4017 => MutableRef::Box(box FrameCounterRegister(apu.frame_counter())),
 
             0x4014 => MutableRef::Box(box OAMDMA(ppu.clone())),
             0x4016 => controller.unwrap_or_else(|| MutableRef::Box(box ()))
@@ -165,12 +315,16 @@ impl MemoryMappedIO for () {
         0
     }
     fn write(&mut self, _: &mut dyn Memory, _: u8) {}
+    fn open_bus_mask(&self) -> u8 {
+        0xE0
+    }
 }
 
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 
 pub struct Clock {
+    nanos_per_cycle: u32,
     start: Instant,
     should_have_elapsed: Duration,
     total_sleep_time: Duration,
@@ -178,7 +332,12 @@ pub struct Clock {
 
 impl Clock {
     pub fn start() -> Clock {
+        Clock::for_region(Region::NTSC)
+    }
+
+    pub fn for_region(region: Region) -> Clock {
         Clock {
+            nanos_per_cycle: region.nanos_per_cpu_cycle(),
             start: Instant::now(),
             should_have_elapsed: Duration::new(0, 0),
             total_sleep_time: Duration::new(0, 0),
@@ -187,7 +346,7 @@ impl Clock {
 
     pub fn tick(&mut self, cycles: u32) {
         self.should_have_elapsed =
-            self.should_have_elapsed + Duration::new(0, cycles * NANOS_PER_CLOCK_CYCLE);
+            self.should_have_elapsed + Duration::new(0, cycles * self.nanos_per_cycle);
         let elapsed = self.start.elapsed();
         if self.should_have_elapsed > elapsed {
             let sleep_time = self.should_have_elapsed - elapsed;
@@ -195,6 +354,14 @@ impl Clock {
             self.total_sleep_time += sleep_time;
         }
     }
+
+    /**
+     * Follows the time while something else paces the emulation, so `tick`
+     * carries on from now instead of catching up.
+     */
+    pub fn keep_up(&mut self) {
+        self.should_have_elapsed = self.start.elapsed();
+    }
 }
 
 use std::fmt::{Display, Error, Formatter};
@@ -218,7 +385,8 @@ impl Display for Clock {
 #[cfg(test)]
 mod test {
 
-    use super::{Clock, NANOS_PER_CLOCK_CYCLE};
+    use super::Clock;
+    use region::Region;
     use std::time::{Duration, Instant};
     #[test]
     fn clock_test() {
@@ -227,14 +395,14 @@ mod test {
         clock.tick(10000);
 
         let elapsed = start.elapsed();
-        let expected_duration = Duration::new(0, 10000 * NANOS_PER_CLOCK_CYCLE);
+        let expected_duration = Duration::new(0, 10000 * Region::NTSC.nanos_per_cpu_cycle());
         assert!(
             elapsed >= expected_duration,
             "Should take at least {:?} but took {:?}",
             expected_duration,
             elapsed
         );
-        let expected_max_duration = Duration::new(0, 100000 * NANOS_PER_CLOCK_CYCLE);
+        let expected_max_duration = Duration::new(0, 100000 * Region::NTSC.nanos_per_cpu_cycle());
         assert!(
             elapsed <= expected_max_duration,
             "Should take at most {:?} but took {:?}",
@@ -252,14 +420,14 @@ mod test {
         }
 
         let elapsed = start.elapsed();
-        let expected_duration = Duration::new(0, 10000 * NANOS_PER_CLOCK_CYCLE);
+        let expected_duration = Duration::new(0, 10000 * Region::NTSC.nanos_per_cpu_cycle());
         assert!(
             elapsed >= expected_duration,
             "Should take at least {:?} but took {:?}",
             expected_duration,
             elapsed
         );
-        let expected_max_duration = Duration::new(0, 10500 * NANOS_PER_CLOCK_CYCLE);
+        let expected_max_duration = Duration::new(0, 10500 * Region::NTSC.nanos_per_cpu_cycle());
         assert!(
             elapsed <= expected_max_duration,
             "Should take at most {:?} but took {:?}",
@@ -1,27 +1,122 @@
 use borrow::MutableRef;
 use memory::{Address, Memory, MemoryMappedIO};
+use std::cell::Cell;
 
+const RAM_SIZE: usize = 0x800;
+const IO_REGISTER_COUNT: usize = 0x28;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Page {
+    Ram,
+    IO,
+    Unmapped,
+    Cartridge,
+}
+
+/**
+ * Everything on the CPU bus that isn't a memory mapped register, i.e. the
+ * internal 2K work RAM mirrored up to $1FFF and the cartridge.
+ */
+struct Bus {
+    ram: [u8; RAM_SIZE],
+    cartridge: Box<dyn Memory>,
+}
+
+impl Memory for Bus {
+    fn get(&self, address: Address, sub_cycle: u8) -> u8 {
+        if address < 0x2000 {
+            self.ram[address as usize & (RAM_SIZE - 1)]
+        } else {
+            self.cartridge.get(address, sub_cycle)
+        }
+    }
+
+    fn set(&mut self, address: Address, value: u8, sub_cycle: u8) {
+        if address < 0x2000 {
+            self.ram[address as usize & (RAM_SIZE - 1)] = value;
+        } else {
+            self.cartridge.set(address, value, sub_cycle);
+        }
+    }
+}
+
+/**
+ * The CPU address space:
+ *  $0000-$1FFF 2K internal RAM, mirrored four times
+ *  $2000-$3FFF PPU registers, mirrored every 8 bytes
+ *  $4000-$401F APU and I/O registers
+ *  $4020-$5FFF unmapped (open bus)
+ *  $6000-$FFFF cartridge, routed to the mapper
+ */
 pub struct CPUMemory<'a> {
-    memory: Box<dyn Memory>,
-    io_registers: Vec<(u16, MutableRef<'a, dyn MemoryMappedIO>)>,
+    bus: Bus,
+    pages: [Page; 0x100],
+    io_registers: Vec<Option<MutableRef<'a, dyn MemoryMappedIO>>>,
+    open_bus: Cell<u8>,
 }
 
 impl<'a> CPUMemory<'a> {
     pub fn new(
-        memory: Box<dyn Memory>,
+        cartridge: Box<dyn Memory>,
         io_registers: Vec<(u16, MutableRef<'a, dyn MemoryMappedIO>)>,
     ) -> CPUMemory<'a> {
+        let mut pages = [Page::Unmapped; 0x100];
+        for (page, entry) in pages.iter_mut().enumerate() {
+            *entry = match page {
+                0x00..=0x1F => Page::Ram,
+                0x20..=0x40 => Page::IO,
+                0x41..=0x5F => Page::Unmapped,
+                _ => Page::Cartridge,
+            };
+        }
+        let mut registers: Vec<Option<MutableRef<'a, dyn MemoryMappedIO>>> =
+            (0..IO_REGISTER_COUNT).map(|_| None).collect();
+        for (address, register) in io_registers {
+            match CPUMemory::io_index(address) {
+                Some(index) => registers[index] = Some(register),
+                None => panic!("0x{:04x} is not a memory mapped I/O address", address),
+            }
+        }
         CPUMemory {
-            memory: memory,
-            io_registers: io_registers,
+            bus: Bus {
+                ram: [0; RAM_SIZE],
+                cartridge: cartridge,
+            },
+            pages: pages,
+            io_registers: registers,
+            open_bus: Cell::new(0),
         }
     }
 
-    fn translate(&self, address: Address) -> Address {
-        if address >= 0x2008 && address < 0x4000 {
-            0x2000 + (address & 0x7)
+    /**
+     * The last value that was read from or written to the data bus.
+     */
+    pub fn open_bus(&self) -> u8 {
+        self.open_bus.get()
+    }
+
+    /**
+     * Reads a value without any side effects, i.e. memory mapped registers are
+     * not read and the data bus is left untouched.
+     */
+    pub fn peek(&self, address: Address) -> u8 {
+        match self.page(address) {
+            Page::Ram | Page::Cartridge => self.bus.get(address, 0),
+            Page::IO | Page::Unmapped => self.open_bus.get(),
+        }
+    }
+
+    fn page(&self, address: Address) -> Page {
+        self.pages[(address >> 8) as usize]
+    }
+
+    fn io_index(address: Address) -> Option<usize> {
+        if address >= 0x2000 && address < 0x4000 {
+            Some((address & 0x7) as usize)
+        } else if address >= 0x4000 && address < 0x4020 {
+            Some(8 + (address & 0x1F) as usize)
         } else {
-            address
+            None
         }
     }
 }
@@ -35,37 +130,50 @@ impl<'a, 'b> Debug for CPUMemoryReference<'a, 'b> {
         formatter.write_fmt(format_args!(
             "0x{:04x} -> 0x{:x}",
             self.0,
-            self.1.memory.get(self.0, 0)
+            self.1.peek(self.0)
         ))
     }
 }
 
-use std::borrow::BorrowMut;
 impl<'a> Memory for CPUMemory<'a> {
     fn get(&self, address: Address, sub_cycle: u8) -> u8 {
-        let address = self.translate(address);
-        if address < 0x2000 {
-            self.memory.get(address, sub_cycle)
-        } else {
-            self.io_registers
-                .iter()
-                .find(|e| e.0 == address)
-                .map(|e| e.1.read_at_cycle(self.memory.as_ref(), sub_cycle))
-                .unwrap_or_else(|| self.memory.get(address, sub_cycle))
-        }
+        let open_bus = self.open_bus.get();
+        let value = match self.page(address) {
+            Page::Ram | Page::Cartridge => self.bus.get(address, sub_cycle),
+            Page::IO => {
+                let register = CPUMemory::io_index(address)
+                    .and_then(|index| self.io_registers[index].as_ref());
+                match register {
+                    Some(register) => {
+                        let mask = register.open_bus_mask();
+                        if mask == 0xFF {
+                            open_bus
+                        } else {
+                            let value = register.read_at_cycle(&self.bus, sub_cycle);
+                            (value & !mask) | (open_bus & mask)
+                        }
+                    }
+                    None => open_bus,
+                }
+            }
+            Page::Unmapped => open_bus,
+        };
+        self.open_bus.set(value);
+        value
     }
 
     fn set(&mut self, address: Address, value: u8, sub_cycles: u8) {
-        let address = self.translate(address);
-        if address < 0x2000 {
-            self.memory.set(address, value, sub_cycles);
-        } else {
-            if let Some(entry) = self.io_registers.iter_mut().find(|e| e.0 == address) {
-                let memory: &mut dyn Memory = self.memory.borrow_mut();
-                entry.1.write_at_cycle(memory, value, sub_cycles);
-            } else {
-                self.memory.set(address, value, sub_cycles);
+        self.open_bus.set(value);
+        match self.page(address) {
+            Page::Ram | Page::Cartridge => self.bus.set(address, value, sub_cycles),
+            Page::IO => {
+                if let Some(index) = CPUMemory::io_index(address) {
+                    if let Some(ref mut register) = self.io_registers[index] {
+                        register.write_at_cycle(&mut self.bus, value, sub_cycles);
+                    }
+                }
             }
+            Page::Unmapped => {}
         }
     }
 }
@@ -85,6 +193,7 @@ macro_rules! cpu_memory {
 
 #[cfg(test)]
 mod test {
+    use super::CPUMemory;
     use memory::{BasicMemory, Memory};
     use std::cell::{Cell, RefCell};
     use std::rc::Rc;
@@ -231,4 +340,100 @@ mod test {
         assert_eq!(&writes, &register6.borrow().writes);
         assert_eq!(&writes, &register7.borrow().writes);
     }
+
+    #[test]
+    fn internal_ram_should_be_mirrored_four_times() {
+        let mut memory = CPUMemory::new(box BasicMemory::new(), vec![]);
+        memory.set(0x0801, 0x11, 0);
+        assert_eq!(0x11, memory.get(0x0001, 0));
+        assert_eq!(0x11, memory.get(0x1001, 0));
+        assert_eq!(0x11, memory.get(0x1801, 0));
+
+        memory.set(0x1FFF, 0x22, 0);
+        assert_eq!(0x22, memory.get(0x07FF, 0));
+    }
+
+    #[test]
+    fn cartridge_space_should_be_routed_to_the_cartridge() {
+        let cartridge = memory!(
+            0x6000 => 0x01,
+            0x8000 => 0x02,
+            0xFFFF => 0x03
+        );
+        let mut memory = CPUMemory::new(box cartridge, vec![]);
+        assert_eq!(0x01, memory.get(0x6000, 0));
+        assert_eq!(0x02, memory.get(0x8000, 0));
+        assert_eq!(0x03, memory.get(0xFFFF, 0));
+
+        memory.set(0x0000, 0x04, 0);
+        memory.set(0x6001, 0x05, 0);
+        assert_eq!(0x00, memory.bus.cartridge.get(0x0000, 0));
+        assert_eq!(0x05, memory.bus.cartridge.get(0x6001, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn registers_outside_of_the_io_space_should_be_rejected() {
+        cpu_memory!(
+            box BasicMemory::new(),
+            0x8000 => MutableRef::Box(box PartialRegister(0x00, 0x00))
+        );
+    }
+
+    struct PartialRegister(u8, u8);
+
+    impl MemoryMappedIO for PartialRegister {
+        fn read(&self, _: &dyn Memory) -> u8 {
+            self.0
+        }
+
+        fn write(&mut self, _: &mut dyn Memory, _: u8) {}
+
+        fn open_bus_mask(&self) -> u8 {
+            self.1
+        }
+    }
+
+    #[test]
+    fn unmapped_addresses_should_return_last_value_on_the_data_bus() {
+        let mut memory = CPUMemory::new(box BasicMemory::new(), vec![]);
+        memory.set(0x0010, 0xA5, 0);
+        assert_eq!(0xA5, memory.get(0x4018, 0));
+        assert_eq!(0xA5, memory.open_bus());
+
+        memory.set(0x5000, 0x3C, 0);
+        assert_eq!(0x3C, memory.get(0x5000, 0));
+        assert_eq!(0x00, memory.get(0x0010 + 1, 0));
+        assert_eq!(0x00, memory.get(0x5000, 0));
+    }
+
+    #[test]
+    fn write_only_registers_should_return_last_value_on_the_data_bus() {
+        let mut memory = cpu_memory!(
+            box BasicMemory::new(),
+            0x4000 => MutableRef::Box(box PartialRegister(0x12, 0xFF))
+        );
+        memory.set(0x4000, 0x42, 0);
+        assert_eq!(0x42, memory.get(0x4000, 0));
+
+        memory.set(0x0000, 0x99, 0);
+        memory.get(0x0000, 0);
+        assert_eq!(0x99, memory.get(0x4000, 0));
+    }
+
+    #[test]
+    fn undriven_bits_should_come_from_the_data_bus() {
+        let mut memory = cpu_memory!(
+            box BasicMemory::new(),
+            0x4016 => MutableRef::Box(box PartialRegister(0x01, 0xE0))
+        );
+        memory.set(0x0000, 0x40, 0);
+        memory.get(0x0000, 0);
+        assert_eq!(0x41, memory.get(0x4016, 0));
+
+        memory.set(0x0000, 0xFE, 0);
+        memory.get(0x0000, 0);
+        assert_eq!(0xE1, memory.get(0x4016, 0));
+        assert_eq!(0xE1, memory.open_bus());
+    }
 }
@@ -1,3 +1,5 @@
+extern crate rand;
+
 pub type Address = u16;
 use std::iter::Iterator;
 use std::ops::{Index, Range};
@@ -21,6 +23,42 @@ pub trait Memory {
     }
 }
 
+/**
+ * The contents of RAM after power on.
+ */
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RamPattern {
+    Zeros,
+    Ones,
+    Random,
+}
+
+impl RamPattern {
+    pub fn fill(&self, memory: &mut dyn Memory, range: Range<Address>) {
+        for address in range {
+            let value = match *self {
+                RamPattern::Zeros => 0x00,
+                RamPattern::Ones => 0xFF,
+                RamPattern::Random => rand::random::<u8>(),
+            };
+            memory.set(address, value, 0);
+        }
+    }
+}
+
+use std::str::FromStr;
+impl FromStr for RamPattern {
+    type Err = String;
+    fn from_str(s: &str) -> Result<RamPattern, String> {
+        match s {
+            "zeros" => Ok(RamPattern::Zeros),
+            "ones" => Ok(RamPattern::Ones),
+            "random" => Ok(RamPattern::Random),
+            _ => Err(format!("Unknown RAM pattern '{}'", s)),
+        }
+    }
+}
+
 pub struct BasicMemory {
     data: Vec<u8>,
 }
@@ -61,6 +99,15 @@ pub trait MemoryMappedIO {
     fn write_at_cycle(&mut self, memory: &mut dyn Memory, value: u8, _: u8) {
         self.write(memory, value)
     }
+
+    /**
+     * Bits that are not driven by the register when read. These bits keep
+     * whatever value was last seen on the CPU data bus (open bus).
+     * A write-only register leaves all bits undriven.
+     */
+    fn open_bus_mask(&self) -> u8 {
+        0x00
+    }
 }
 
 #[macro_export]
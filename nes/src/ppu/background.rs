@@ -0,0 +1,107 @@
+/**
+ * The background part of the rendering pipeline. The next tile is fetched into
+ * the latches while the current one is shifted out, and every eighth dot the
+ * latches are moved into the low byte of the shift registers.
+ */
+pub struct Background {
+    pub name_table: u8,
+    pub attribute: u8,
+    pub pattern_low: u8,
+    pub pattern_high: u8,
+
+    pattern_shift_low: u16,
+    pattern_shift_high: u16,
+    attribute_shift_low: u16,
+    attribute_shift_high: u16,
+}
+
+impl Background {
+    pub fn new() -> Background {
+        Background {
+            name_table: 0,
+            attribute: 0,
+            pattern_low: 0,
+            pattern_high: 0,
+
+            pattern_shift_low: 0,
+            pattern_shift_high: 0,
+            attribute_shift_low: 0,
+            attribute_shift_high: 0,
+        }
+    }
+
+    pub fn reload(&mut self) {
+        self.pattern_shift_low = (self.pattern_shift_low & 0xFF00) | self.pattern_low as u16;
+        self.pattern_shift_high = (self.pattern_shift_high & 0xFF00) | self.pattern_high as u16;
+        self.attribute_shift_low =
+            (self.attribute_shift_low & 0xFF00) | if self.attribute & 0x01 != 0 { 0xFF } else { 0 };
+        self.attribute_shift_high = (self.attribute_shift_high & 0xFF00)
+            | if self.attribute & 0x02 != 0 { 0xFF } else { 0 };
+    }
+
+    pub fn shift(&mut self) {
+        self.pattern_shift_low <<= 1;
+        self.pattern_shift_high <<= 1;
+        self.attribute_shift_low <<= 1;
+        self.attribute_shift_high <<= 1;
+    }
+
+    /**
+     * Index into the background palettes ($3F00-$3F0F) of the pixel selected by
+     * fine x. Transparent pixels always return 0, i.e. the backdrop colour.
+     */
+    pub fn pixel(&self, fine_x: u8) -> u8 {
+        let bit = 15 - fine_x;
+        let colour = (((self.pattern_shift_high >> bit) & 0x01) << 1)
+            | ((self.pattern_shift_low >> bit) & 0x01);
+        if colour == 0 {
+            return 0;
+        }
+        let palette = (((self.attribute_shift_high >> bit) & 0x01) << 1)
+            | ((self.attribute_shift_low >> bit) & 0x01);
+        ((palette << 2) | colour) as u8
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Background;
+
+    fn load(background: &mut Background, low: u8, high: u8, attribute: u8) {
+        background.pattern_low = low;
+        background.pattern_high = high;
+        background.attribute = attribute;
+        background.reload();
+    }
+
+    #[test]
+    fn reloaded_tile_should_be_shifted_out_after_eight_dots() {
+        let mut background = Background::new();
+        load(&mut background, 0b1000_0001, 0b0000_0001, 2);
+        assert_eq!(0, background.pixel(0));
+        for _ in 0..8 {
+            background.shift();
+        }
+        load(&mut background, 0xFF, 0xFF, 1);
+
+        let pixels: Vec<u8> = (0..8).map(|x| background.pixel(x)).collect();
+        assert_eq!(vec![0b10_01, 0, 0, 0, 0, 0, 0, 0b10_11], pixels);
+    }
+
+    #[test]
+    fn fine_x_should_select_pixels_from_the_next_tile() {
+        let mut background = Background::new();
+        load(&mut background, 0x00, 0x00, 0);
+        for _ in 0..8 {
+            background.shift();
+        }
+        load(&mut background, 0xFF, 0x00, 3);
+        for _ in 0..6 {
+            background.shift();
+        }
+
+        assert_eq!(0, background.pixel(1));
+        assert_eq!(0b11_01, background.pixel(2));
+        assert_eq!(0b11_01, background.pixel(7));
+    }
+}
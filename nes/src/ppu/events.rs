@@ -0,0 +1,114 @@
+use std::fmt::{Display, Error, Formatter};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EventKind {
+    /**
+     * CPU write to one of the PPU registers $2000-$2007.
+     */
+    RegisterWrite(u16),
+    Nmi,
+    Irq,
+}
+
+/**
+ * Something that happened at a given dot of the frame, for debugging raster effects.
+ */
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Event {
+    pub scanline: u32,
+    pub dot: u32,
+    pub kind: EventKind,
+    pub value: u8,
+    /**
+     * Address of the CPU instruction that caused the event.
+     */
+    pub program_counter: u16,
+}
+
+impl Display for Event {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        formatter.write_fmt(format_args!(
+            "Scanline {:3}, dot {:3}: ",
+            self.scanline, self.dot
+        ))?;
+        match self.kind {
+            EventKind::RegisterWrite(register) => {
+                formatter.write_fmt(format_args!("${:04X} <- 0x{:02X}", register, self.value))?
+            }
+            EventKind::Nmi => formatter.write_str("NMI")?,
+            EventKind::Irq => formatter.write_str("IRQ")?,
+        }
+        formatter.write_fmt(format_args!(" (PC 0x{:04X})", self.program_counter))
+    }
+}
+
+/**
+ * The events of the frame being rendered and of the one before it.
+ */
+pub struct EventLog {
+    current_frame: Vec<Event>,
+    last_frame: Vec<Event>,
+}
+
+impl EventLog {
+    pub fn new() -> EventLog {
+        EventLog {
+            current_frame: vec![],
+            last_frame: vec![],
+        }
+    }
+
+    pub fn push(&mut self, event: Event) {
+        self.current_frame.push(event);
+    }
+
+    pub fn next_frame(&mut self) {
+        self.last_frame = ::std::mem::take(&mut self.current_frame);
+    }
+
+    pub fn current_frame(&self) -> &[Event] {
+        &self.current_frame
+    }
+
+    pub fn last_frame(&self) -> &[Event] {
+        &self.last_frame
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Event, EventKind, EventLog};
+
+    fn event(scanline: u32) -> Event {
+        Event {
+            scanline: scanline,
+            dot: 4,
+            kind: EventKind::RegisterWrite(0x2005),
+            value: 0x1F,
+            program_counter: 0xC123,
+        }
+    }
+
+    #[test]
+    fn events_should_move_to_the_last_frame_when_the_frame_ends() {
+        let mut log = EventLog::new();
+        log.push(event(10));
+        log.next_frame();
+        log.push(event(20));
+
+        assert_eq!(&[event(10)], log.last_frame());
+        assert_eq!(&[event(20)], log.current_frame());
+
+        log.next_frame();
+        assert_eq!(&[event(20)], log.last_frame());
+        assert!(log.current_frame().is_empty());
+    }
+
+    #[test]
+    fn event_should_show_position_register_and_program_counter() {
+        assert_eq!(
+            "Scanline  10, dot   4: $2005 <- 0x1F (PC 0xC123)",
+            event(10).to_string()
+        );
+    }
+}
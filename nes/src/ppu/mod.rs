@@ -1,6 +1,8 @@
 pub use self::ppu::PPU;
 
 pub mod attributetable;
+mod background;
+pub mod events;
 pub mod name_tables;
 pub mod pattern;
 mod ppu;
@@ -8,4 +10,5 @@ pub mod ppumemory;
 pub mod ppuregisters;
 pub mod screen;
 pub mod sprite;
+pub mod viewer;
 pub mod vram_registers;
@@ -1,9 +1,11 @@
 use memory::Memory;
-use ppu::ppumemory;
+use ppu::background::Background;
+use ppu::events::{Event, EventKind, EventLog};
 use ppu::ppumemory::PPUMemory;
-use ppu::screen::{PixelBuffer, Rectangle, Screen, COLOUR_PALETTE};
-use ppu::sprite::{Sprite, Sprites};
+use ppu::screen::{ColourPalette, Frame, Screen, SCREEN_HEIGHT, SCREEN_WIDTH};
+use ppu::sprite::{ScanlineSprite, Sprite, Sprites, ATTRIBUTE_BITS};
 use ppu::vram_registers::VRAMRegisters;
+use region::Region;
 
 struct PPUCtrl {
     value: u8,
@@ -15,13 +17,21 @@ impl PPUCtrl {
     }
 
     fn background_pattern_table(&self) -> u16 {
-        ((self.value & 0x10) as u16) << 4
+        ((self.value & 0x10) as u16) << 8
     }
 
     fn sprite_pattern_table(&self) -> u16 {
         ((self.value & 0x08) as u16) << 9
     }
 
+    fn sprite_height(&self) -> u32 {
+        if self.value & 0x20 == 0 {
+            8
+        } else {
+            16
+        }
+    }
+
     fn nmi_enabled(&self) -> bool {
         (self.value & 0x80) != 0
     }
@@ -43,6 +53,37 @@ impl PPUMask {
     fn is_rendering_enabled(&self) -> bool {
         self.value & 0x18 > 0
     }
+
+    fn show_background_left(&self) -> bool {
+        self.value & 0x02 != 0
+    }
+
+    fn show_sprites_left(&self) -> bool {
+        self.value & 0x04 != 0
+    }
+
+    fn show_background(&self) -> bool {
+        self.value & 0x08 != 0
+    }
+
+    fn show_sprites(&self) -> bool {
+        self.value & 0x10 != 0
+    }
+
+    /**
+     * Greyscale mode only keeps the column of grey colours in the palette.
+     */
+    fn colour_mask(&self) -> u8 {
+        if self.value & 0x01 != 0 {
+            0x30
+        } else {
+            0x3F
+        }
+    }
+
+    fn emphasis(&self) -> u16 {
+        (self.value >> 5) as u16
+    }
 }
 
 trait PPUStatus {
@@ -64,21 +105,33 @@ pub struct PPU {
     pending_nmi: u32,
     nmi_triggered: bool,
     nmi_active: bool,
-    frame_skipped: bool,
     memory: PPUMemory,
     vram_registers: VRAMRegisters,
     temp_vram_read_buffer: u8,
+    io_latch: u8,
+    io_latch_refreshed: [u32; 8],
+    frame_count: u32,
 
-    vram_changed: bool,
-
+    region: Region,
     cycle_count: u32,
+    cycle_fraction: u32,
     cycles_already_executed: u32,
     should_update_screen: bool,
-    mirroring: ppumemory::Mirroring,
 
     sprites: Sprites,
+    secondary_oam: [u8; 8 * 4],
+    scanline_sprites: Vec<ScanlineSprite>,
+    sprite_zero_on_scanline: bool,
+    sprite_limit: bool,
+    background: Background,
+    frame: Box<[u16; SCREEN_WIDTH * SCREEN_HEIGHT]>,
+    palette: ColourPalette,
 
     odd_flag: bool,
+    warming_up: bool,
+
+    event_log: Option<EventLog>,
+    program_counter: u16,
 }
 
 use std::fmt::{Display, Error, Formatter};
@@ -129,20 +182,13 @@ impl Display for PPU {
     }
 }
 
-const PPU_CYCLES_PER_CPU_CYCLE: u32 = 3;
 const PPU_CYCLES_PER_SCANLINE: u32 = 341;
-const SCANLINES_PER_VBLANK: u32 = 20;
-const SCANLINES_PER_FRAME: u32 = 262;
 const VISIBLE_SCANLINES: u32 = 240;
-const POST_RENDER_LINES: u32 = 1;
-const VBLANK_CYCLE: u32 = (VISIBLE_SCANLINES + POST_RENDER_LINES) * PPU_CYCLES_PER_SCANLINE + 1; //82 182
-const NMI_CYCLE: u32 = VBLANK_CYCLE + 3; //82 185
-const VBLANK_CLEAR_CYCLE: u32 =
-    (VISIBLE_SCANLINES + POST_RENDER_LINES + SCANLINES_PER_VBLANK) * PPU_CYCLES_PER_SCANLINE + 1; //89 002
+//About 600 ms
+const IO_LATCH_DECAY_FRAMES: u32 = 36;
 
 impl PPU {
     pub fn new(memory: PPUMemory) -> PPU {
-        let mirroring = memory.mirroring();
         PPU {
             control_register: PPUCtrl::new(),
             mask_register: PPUMask { value: 0 },
@@ -152,26 +198,70 @@ impl PPU {
             pending_nmi: 0,
             nmi_triggered: false,
             nmi_active: false,
-            frame_skipped: false,
             memory: memory,
             vram_registers: VRAMRegisters::new(),
             temp_vram_read_buffer: 0,
+            io_latch: 0,
+            io_latch_refreshed: [0; 8],
+            frame_count: 0,
+            event_log: None,
+            program_counter: 0,
 
-            vram_changed: true,
-
+            region: Region::NTSC,
             cycle_count: 0,
+            cycle_fraction: 0,
             cycles_already_executed: 0,
             should_update_screen: false,
-            mirroring: mirroring,
 
             sprites: Sprites::new(),
+            secondary_oam: [0xFF; 8 * 4],
+            scanline_sprites: Vec::with_capacity(64),
+            sprite_zero_on_scanline: false,
+            sprite_limit: true,
+            background: Background::new(),
+            frame: box [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            palette: ColourPalette::default(),
 
             odd_flag: false,
+            warming_up: false,
         }
     }
 
+    /**
+     * Soft reset. PPUCTRL, PPUMASK and the scroll latches are cleared and writes to
+     * $2000, $2001, $2005 and $2006 are ignored until the end of the next vblank.
+     */
+    pub fn reset(&mut self) {
+        self.control_register.value = 0;
+        self.mask_register.value = 0;
+        self.vram_registers.reset();
+        self.temp_vram_read_buffer = 0;
+        self.pending_nmi = 0;
+        self.nmi_active = false;
+        self.odd_flag = false;
+        self.warming_up = true;
+    }
+
+    pub fn power_cycle(&mut self) {
+        self.reset();
+        self.status_register = 0;
+        self.set_io_latch(0);
+        self.vram_registers = VRAMRegisters::new();
+        self.cycle_count = 0;
+        self.cycle_fraction = 0;
+        self.cycles_already_executed = 0;
+        self.vblank_triggered = false;
+        self.vblank_cleared = false;
+        self.nmi_triggered = false;
+        self.sprites.set_address(0);
+    }
+
     pub fn set_ppu_ctrl_at_cycle(&mut self, value: u8, sub_cycle: u8) {
-        self.partially_update((sub_cycle as u32) * PPU_CYCLES_PER_CPU_CYCLE + 3);
+        let ppu_cycles = self.ppu_cycles(sub_cycle as u32);
+        self.partially_update(ppu_cycles + 3);
+        if self.warming_up {
+            return;
+        }
 
         if !self.control_register.nmi_enabled()
             && (value & 0x80 != 0)
@@ -191,66 +281,221 @@ impl PPU {
         self.control_register.value
     }
 
+    pub fn background_pattern_table(&self) -> u16 {
+        self.control_register.background_pattern_table()
+    }
+
+    pub fn sprite_pattern_table(&self) -> u16 {
+        self.control_register.sprite_pattern_table()
+    }
+
+    pub fn sprite_height(&self) -> u32 {
+        self.control_register.sprite_height()
+    }
+
+    /**
+     * Position of the top left corner of the next frame in the 512x480 area
+     * covered by the four name tables.
+     */
+    pub fn scroll(&self) -> (u16, u16) {
+        (
+            self.vram_registers.temporary_absolute_x_scroll(),
+            self.vram_registers.temporary_absolute_y_scroll(),
+        )
+    }
+
     pub fn set_ppu_mask(&mut self, value: u8, sub_cycle: u8) {
-        self.partially_update((sub_cycle as u32) * PPU_CYCLES_PER_CPU_CYCLE + 3);
+        let ppu_cycles = self.ppu_cycles(sub_cycle as u32);
+        self.partially_update(ppu_cycles + 3);
+        if self.warming_up {
+            return;
+        }
         self.mask_register.value = value;
     }
 
     pub fn status(&mut self, sub_cycle: u8) -> u8 {
-        self.partially_update((sub_cycle as u32) * PPU_CYCLES_PER_CPU_CYCLE + 2);
-        let status_register = self.status_register;
+        let ppu_cycles = self.ppu_cycles(sub_cycle as u32);
+        self.partially_update(ppu_cycles + 2);
+        let status_register = (self.status_register & 0xE0) | (self.io_latch() & 0x1F);
+        self.refresh_io_latch(status_register, 0xE0);
         self.status_register &= 0x7F;
         self.vram_registers.reset_write_toggle();
-        if self.cycle_count == VBLANK_CYCLE - 1 {
+        let vblank_cycle = self.vblank_cycle();
+        if self.cycle_count == vblank_cycle - 1 {
             self.vblank_triggered = true;
             self.nmi_triggered = true;
-        } else if self.cycle_count == VBLANK_CYCLE || self.cycle_count == VBLANK_CYCLE + 1 {
+        } else if self.cycle_count == vblank_cycle || self.cycle_count == vblank_cycle + 1 {
             self.nmi_triggered = true;
         }
         return status_register;
     }
 
-    pub fn set_vram(&mut self, value: u8) {
+    pub fn set_vram_at_cycle(&mut self, value: u8, sub_cycle: u8) {
+        let ppu_cycles = self.ppu_cycles(sub_cycle as u32);
+        self.partially_update(ppu_cycles + 3);
+        if self.warming_up {
+            return;
+        }
         self.vram_registers.set_vram(value);
     }
 
+    pub fn set_vram(&mut self, value: u8) {
+        self.set_vram_at_cycle(value, 0)
+    }
+
     pub fn vram(&self) -> u16 {
         self.vram_registers.current
     }
 
-    pub fn set_scroll(&mut self, value: u8) {
+    /**
+     * Only the temporary address and fine x are written, the new scroll
+     * position is picked up by the rendering pipeline at dot 257 (horizontal)
+     * and during the pre-render line (vertical).
+     */
+    pub fn set_scroll_at_cycle(&mut self, value: u8, sub_cycle: u8) {
+        let ppu_cycles = self.ppu_cycles(sub_cycle as u32);
+        self.partially_update(ppu_cycles + 3);
+        if self.warming_up {
+            return;
+        }
         self.vram_registers.write_scroll(value);
     }
 
+    pub fn set_scroll(&mut self, value: u8) {
+        self.set_scroll_at_cycle(value, 0)
+    }
+
+    pub fn write_to_vram_at_cycle(&mut self, value: u8, sub_cycle: u8) {
+        let ppu_cycles = self.ppu_cycles(sub_cycle as u32);
+        self.partially_update(ppu_cycles + 3);
+        self.write_to_vram(value);
+    }
+
     pub fn write_to_vram(&mut self, value: u8) {
-        self.vram_changed = true;
         self.memory.set(self.vram_registers.current, value, 0);
-        self.vram_registers.current += self.control_register.vram_pointer_increment();
+        self.increment_vram();
+    }
+
+    pub fn read_from_vram_at_cycle(&mut self, sub_cycle: u8) -> u8 {
+        let ppu_cycles = self.ppu_cycles(sub_cycle as u32);
+        self.partially_update(ppu_cycles + 2);
+        self.read_from_vram()
     }
 
+    /**
+     * Reads go through a buffer, except for the palette which is returned
+     * directly. Palette entries are only 6 bits, so the upper bits come from
+     * the I/O latch.
+     */
     pub fn read_from_vram(&mut self) -> u8 {
         let current_vram = self.vram_registers.current;
-        let value = if current_vram >= 0x3F00 {
+        if current_vram >= 0x3F00 {
             self.temp_vram_read_buffer = self.memory.get(current_vram - 0x1000, 0);
-            self.memory.get(current_vram, 0)
+            let colour = self.memory.get(current_vram, 0) & self.mask_register.colour_mask();
+            self.refresh_io_latch(colour, 0x3F);
         } else {
             let value = self.temp_vram_read_buffer;
             self.temp_vram_read_buffer = self.memory.get(current_vram, 0);
-            value
+            self.set_io_latch(value);
+        }
+        self.increment_vram();
+        return self.io_latch();
+    }
+
+    /**
+     * While rendering, the VRAM address is also used for fetching tiles, so a
+     * $2007 access increments both coarse x and y instead of adding 1 or 32.
+     */
+    fn increment_vram(&mut self) {
+        if self.is_rendering() {
+            self.vram_registers.horizontal_increment();
+            self.vram_registers.vertical_increment();
+        } else {
+            self.vram_registers.current += self.control_register.vram_pointer_increment();
+        }
+    }
+
+    fn is_rendering(&self) -> bool {
+        let scanline = self.scanline();
+        self.mask_register.is_rendering_enabled()
+            && (scanline < VISIBLE_SCANLINES || scanline == self.pre_render_scanline())
+    }
+
+    /**
+     * While rendering, $2004 returns whatever the sprite evaluation is
+     * currently reading instead of the byte at OAMADDR.
+     */
+    pub fn read_oam_data(&mut self, sub_cycle: u8) -> u8 {
+        let ppu_cycles = self.ppu_cycles(sub_cycle as u32);
+        self.partially_update(ppu_cycles + 2);
+        let value = if self.is_rendering() {
+            self.oam_bus()
+        } else {
+            self.sprites.read_byte()
         };
-        self.vram_registers.current += self.control_register.vram_pointer_increment();
-        return value;
+        self.set_io_latch(value);
+        value
     }
 
-    pub fn load(&mut self, base_address: u16, rom: &[u8]) {
-        let current_vram = self.vram_registers.current;
-        self.vram_registers.current = base_address;
+    fn oam_bus(&self) -> u8 {
+        let dot = self.dot() as usize;
+        if dot == 0 {
+            //Idle dot, the bus still holds the first byte of secondary OAM
+            self.secondary_oam[0]
+        } else if dot <= 64 {
+            //Secondary OAM is cleared
+            0xFF
+        } else if dot <= 256 {
+            //Evaluation reads a y coordinate every other dot, assume no sprite is in range
+            self.sprites[((dot - 65) / 2) % 64][0]
+        } else if dot <= 320 {
+            //Each sprite in secondary OAM is read during 8 dots
+            let byte = ((dot - 257) % 8).min(3);
+            let value = self.secondary_oam[(dot - 257) / 8 * 4 + byte];
+            if byte == 2 {
+                value & ATTRIBUTE_BITS
+            } else {
+                value
+            }
+        } else {
+            self.secondary_oam[0]
+        }
+    }
+
+    /**
+     * The value last written to (or read from) any of the PPU registers.
+     * Reading a write-only register returns this value. The latch is a
+     * capacitance, so a bit fades to 0 if it has not been refreshed for a
+     * while.
+     */
+    pub fn io_latch(&self) -> u8 {
+        (0..8)
+            .filter(|&bit| {
+                self.frame_count.wrapping_sub(self.io_latch_refreshed[bit]) < IO_LATCH_DECAY_FRAMES
+            })
+            .fold(0, |latch, bit| latch | (self.io_latch & (1 << bit)))
+    }
 
-        for &byte in rom {
-            self.write_to_vram(byte);
+    pub fn set_io_latch(&mut self, value: u8) {
+        self.refresh_io_latch(value, 0xFF);
+    }
+
+    /**
+     * Only the bits that are driven by a register read are refreshed.
+     */
+    fn refresh_io_latch(&mut self, value: u8, bits: u8) {
+        self.io_latch = (self.io_latch & !bits) | (value & bits);
+        for bit in 0..8 {
+            if bits & (1 << bit) != 0 {
+                self.io_latch_refreshed[bit] = self.frame_count;
+            }
         }
+    }
 
-        self.vram_registers.current = current_vram;
+    pub fn load(&mut self, base_address: u16, rom: &[u8]) {
+        for (offset, &byte) in rom.iter().enumerate() {
+            self.memory.set(base_address + offset as u16, byte, 0);
+        }
     }
 
     pub fn sprites_mut(&mut self) -> &mut Sprites {
@@ -261,44 +506,88 @@ impl PPU {
         &self.sprites
     }
 
+    pub fn scanline(&self) -> u32 {
+        self.cycle_count / PPU_CYCLES_PER_SCANLINE
+    }
+
+    pub fn dot(&self) -> u32 {
+        self.cycle_count % PPU_CYCLES_PER_SCANLINE
+    }
+
+    /**
+     * Catches up with the CPU before a register access in the middle of an
+     * instruction. `ppu_cycles` is counted from the start of the instruction.
+     */
     fn partially_update(&mut self, ppu_cycles: u32) {
-        self.update(ppu_cycles);
-        self.cycles_already_executed = ppu_cycles;
+        if ppu_cycles > self.cycles_already_executed {
+            let cycles = ppu_cycles - self.cycles_already_executed;
+            self.update(cycles);
+            self.cycles_already_executed = ppu_cycles;
+        }
+    }
+
+    /**
+     * PPU cycles run during the given number of CPU cycles of the current
+     * instruction, including the fraction left over from earlier instructions.
+     */
+    fn ppu_cycles(&self, cpu_cycles: u32) -> u32 {
+        (self.cycle_fraction + cpu_cycles * self.region.ppu_cycles_per_5_cpu_cycles()) / 5
+    }
+
+    /**
+     * Vblank starts at dot 1 of the scanline after the post-render lines,
+     * i.e. at 82 182 on NTSC.
+     */
+    fn vblank_cycle(&self) -> u32 {
+        (VISIBLE_SCANLINES + self.region.post_render_scanlines()) * PPU_CYCLES_PER_SCANLINE + 1
+    }
+
+    fn pre_render_scanline(&self) -> u32 {
+        self.region.scanlines_per_frame() - 1
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
     }
 
     fn update(&mut self, ppu_cycle_count: u32) {
-        self.cycle_count += ppu_cycle_count;
-        if !self.vblank_triggered && self.cycle_count >= VBLANK_CYCLE {
+        let skipped_cycles = self.render(ppu_cycle_count);
+        self.cycle_count += ppu_cycle_count + skipped_cycles;
+        let frame_cycles = self.region.scanlines_per_frame() * PPU_CYCLES_PER_SCANLINE;
+        let nmi_cycle = self.vblank_cycle() + 3;
+        if !self.vblank_triggered && self.cycle_count >= self.vblank_cycle() {
             //VBLANK
             self.status_register = self.status_register | 0x80; //set vblank
             self.vblank_triggered = true;
             self.should_update_screen = true;
-            if !self.nmi_triggered && self.cycle_count >= NMI_CYCLE {
+            if !self.nmi_triggered && self.cycle_count >= nmi_cycle {
                 self.nmi_triggered = true;
                 self.nmi_active = self.control_register.nmi_enabled();
             }
-        } else if !self.nmi_triggered && self.cycle_count >= NMI_CYCLE {
+        } else if !self.nmi_triggered && self.cycle_count >= nmi_cycle {
             self.nmi_triggered = true;
             self.nmi_active = self.control_register.nmi_enabled();
-        } else if !self.vblank_cleared && self.cycle_count >= VBLANK_CLEAR_CYCLE {
-            //VBLANK is over
-            self.status_register = self.status_register & 0x3F;
-            self.vblank_cleared = true;
-        } else if !self.frame_skipped
-            && self.cycle_count >= SCANLINES_PER_FRAME * PPU_CYCLES_PER_SCANLINE - 2
+        } else if !self.vblank_cleared
+            && self.cycle_count >= self.pre_render_scanline() * PPU_CYCLES_PER_SCANLINE + 1
         {
-            self.odd_flag = !self.odd_flag;
-            self.frame_skipped = true;
-            if self.mask_register.is_rendering_enabled() && self.odd_flag {
-                self.cycle_count += 1;
-            }
+            //VBLANK is over, sprite 0 hit and sprite overflow are cleared at the same time
+            self.status_register = self.status_register & 0x1F;
+            self.vblank_cleared = true;
+            self.warming_up = false;
         }
-        if self.cycle_count >= SCANLINES_PER_FRAME * PPU_CYCLES_PER_SCANLINE {
-            self.cycle_count -= SCANLINES_PER_FRAME * PPU_CYCLES_PER_SCANLINE;
+        if self.cycle_count >= frame_cycles {
+            self.cycle_count -= frame_cycles;
             self.vblank_triggered = false;
             self.vblank_cleared = false;
             self.nmi_triggered = false;
-            self.frame_skipped = false;
+            self.frame_count = self.frame_count.wrapping_add(1);
+            if let Some(ref mut event_log) = self.event_log {
+                event_log.next_frame();
+            }
         }
     }
 
@@ -309,151 +598,367 @@ impl PPU {
     where
         T: Screen + Sized,
     {
-        let remaining_cycles =
-            cpu_cycle_count * PPU_CYCLES_PER_CPU_CYCLE - self.cycles_already_executed;
+        let remaining_cycles = self.ppu_cycles(cpu_cycle_count) - self.cycles_already_executed;
+        self.cycle_fraction =
+            (self.cycle_fraction + cpu_cycle_count * self.region.ppu_cycles_per_5_cpu_cycles()) % 5;
         self.cycles_already_executed = 0;
         self.update(remaining_cycles);
         if self.should_update_screen {
             if cfg!(feature = "ppu") {
-                if self.mask_register.is_rendering_enabled() {
-                    self.update_screen(screen);
-                }
+                self.present(screen);
             }
             self.should_update_screen = false;
         }
-        if self.nmi_active {
+        let nmi = if self.nmi_active {
             self.nmi_active = false;
-            return true;
+            true
         } else if self.pending_nmi > 0 {
             self.pending_nmi -= 1;
-            return self.pending_nmi == 0;
+            self.pending_nmi == 0
         } else {
-            return false;
+            false
+        };
+        if nmi {
+            self.log_event(EventKind::Nmi, 0);
         }
+        nmi
     }
 
-    pub fn update_screen<T>(&mut self, screen: &mut T)
-    where
-        T: Screen + Sized,
-    {
-        if self.vram_changed {
-            self.vram_changed = false;
-            screen.update_buffer(|buffer| self.draw_buffer(buffer));
+    /**
+     * Starts or stops recording register writes and interrupts per frame.
+     */
+    pub fn set_event_logging(&mut self, enabled: bool) {
+        self.event_log = if enabled { Some(EventLog::new()) } else { None };
+    }
+
+    pub fn events(&self) -> Option<&EventLog> {
+        self.event_log.as_ref()
+    }
+
+    /**
+     * Address of the instruction the CPU is executing, stored with the logged events.
+     */
+    pub fn set_program_counter(&mut self, program_counter: u16) {
+        self.program_counter = program_counter;
+    }
+
+    pub fn log_event(&mut self, kind: EventKind, value: u8) {
+        let cycle = self.cycle_count;
+        self.log_event_at(cycle, kind, value);
+    }
+
+    fn log_event_at(&mut self, cycle: u32, kind: EventKind, value: u8) {
+        let event = Event {
+            scanline: cycle / PPU_CYCLES_PER_SCANLINE,
+            dot: cycle % PPU_CYCLES_PER_SCANLINE,
+            kind: kind,
+            value: value,
+            program_counter: self.program_counter,
+        };
+        if let Some(ref mut event_log) = self.event_log {
+            event_log.push(event);
         }
-        screen.update_sprites(|buffer| self.update_sprites(buffer));
-        self.vram_registers.copy_temporary_bits();
-        let screen_width: usize = 256;
-        let screen_height: usize = 240;
-        let left: usize = self.vram_registers.current_absolute_x_scroll() as usize;
-        let top: usize = self.vram_registers.current_absolute_y_scroll() as usize;
-        let (area_width, area_height): (usize, usize) = match self.mirroring {
-            ppumemory::Mirroring::Horizontal => (256, 480),
-            ppumemory::Mirroring::Vertical => (512, 240),
-            ppumemory::Mirroring::NoMirroring => (512, 480),
+    }
+
+    /**
+     * Logs a write to $2000-$2007 at the dot it happens, if event logging is on.
+     * The PPU is not caught up, so logging does not change the timing.
+     */
+    pub fn log_register_write(&mut self, register: u16, value: u8, sub_cycle: u8) {
+        if self.event_log.is_some() {
+            let ppu_cycles = self.ppu_cycles(sub_cycle as u32) + 3;
+            let ahead = ppu_cycles.saturating_sub(self.cycles_already_executed);
+            let frame_cycles = self.region.scanlines_per_frame() * PPU_CYCLES_PER_SCANLINE;
+            let cycle = (self.cycle_count + ahead) % frame_cycles;
+            self.log_event_at(cycle, EventKind::RegisterWrite(register), value);
+        }
+    }
+
+    /**
+     * Runs the rendering pipeline for the given number of dots from the current
+     * position in the frame. Returns the dots skipped on the way: on odd NTSC
+     * frames with rendering enabled the pre-render scanline goes from dot 339
+     * straight to dot 0 of scanline 0.
+     */
+    fn render(&mut self, ppu_cycles: u32) -> u32 {
+        let rendering_enabled = self.mask_register.is_rendering_enabled();
+        let pre_render_scanline = self.pre_render_scanline();
+        let mut skipped_cycles = 0;
+        for cycle in self.cycle_count..(self.cycle_count + ppu_cycles) {
+            let cycle = (cycle + skipped_cycles)
+                % (self.region.scanlines_per_frame() * PPU_CYCLES_PER_SCANLINE);
+            let scanline = cycle / PPU_CYCLES_PER_SCANLINE;
+            let dot = cycle % PPU_CYCLES_PER_SCANLINE;
+            if rendering_enabled {
+                self.render_dot(scanline, dot);
+            } else if scanline < VISIBLE_SCANLINES && dot >= 1 && dot <= 256 {
+                self.frame[scanline as usize * SCREEN_WIDTH + dot as usize - 1] =
+                    self.output_colour(0x3F00);
+            }
+            if scanline == pre_render_scanline && dot == 339 {
+                self.odd_flag = !self.odd_flag;
+                if rendering_enabled && self.odd_flag && self.region.skips_dot_on_odd_frames() {
+                    skipped_cycles += 1;
+                }
+            }
+        }
+        skipped_cycles
+    }
+
+    fn render_dot(&mut self, scanline: u32, dot: u32) {
+        let pre_render = scanline == self.pre_render_scanline();
+        if scanline >= VISIBLE_SCANLINES && !pre_render {
+            return;
+        }
+        if (dot >= 2 && dot <= 257) || (dot >= 322 && dot <= 337) {
+            self.background.shift();
+        }
+        if (dot >= 1 && dot <= 256) || (dot >= 321 && dot <= 336) {
+            self.fetch_background((dot - 1) % 8);
+        }
+        if dot == 256 {
+            self.vram_registers.vertical_increment();
+        } else if dot == 257 {
+            self.background.reload();
+            self.vram_registers.copy_horizontal_bits();
+            if pre_render {
+                self.scanline_sprites.clear();
+                self.sprite_zero_on_scanline = false;
+            } else {
+                self.evaluate_sprites(scanline);
+            }
+        } else if pre_render && dot >= 280 && dot <= 304 {
+            self.vram_registers.copy_vertical_bits();
+        }
+        if !pre_render && dot >= 1 && dot <= 256 {
+            self.draw_pixel(scanline as usize, (dot - 1) as u8);
+        }
+    }
+
+    /**
+     * Each tile takes eight dots: name table, attribute table and the two
+     * pattern planes are fetched on every other dot before moving on to the
+     * next tile.
+     */
+    fn fetch_background(&mut self, step: u32) {
+        match step {
+            0 => {
+                self.background.reload();
+                self.background.name_table = self.memory.get(self.vram_registers.tile_address(), 0);
+            }
+            2 => {
+                let attribute = self.memory.get(self.vram_registers.attribute_address(), 0);
+                self.background.attribute =
+                    (attribute >> self.vram_registers.attribute_shift()) & 0x03;
+            }
+            4 => {
+                let address = self.background_pattern_address();
+                self.background.pattern_low = self.memory.get(address, 0);
+            }
+            6 => {
+                let address = self.background_pattern_address();
+                self.background.pattern_high = self.memory.get(address + 8, 0);
+            }
+            7 => self.vram_registers.horizontal_increment(),
+            _ => {}
+        }
+    }
+
+    fn background_pattern_address(&self) -> u16 {
+        self.control_register.background_pattern_table()
+            | ((self.background.name_table as u16) << 4)
+            | self.vram_registers.fine_y() as u16
+    }
+
+    /**
+     * Selects the sprites that are visible on the scanline following the given
+     * one and fetches their pattern rows. Like the secondary OAM of the real
+     * PPU only the first 8 sprites are kept, unless the sprite limit is disabled.
+     */
+    fn evaluate_sprites(&mut self, scanline: u32) {
+        self.scanline_sprites.clear();
+        self.sprite_zero_on_scanline = false;
+        let height = self.control_register.sprite_height();
+        let mut sprite_index = 0;
+        self.secondary_oam = [0xFF; 8 * 4];
+        while sprite_index < 64 && self.scanline_sprites.len() < 8 {
+            let position_y = (&self.sprites[sprite_index]).position_y();
+            if is_on_scanline(position_y, scanline, height) {
+                let slot = self.scanline_sprites.len() * 4;
+                self.secondary_oam[slot..slot + 4].copy_from_slice(&self.sprites[sprite_index]);
+                self.fetch_sprite(sprite_index, scanline);
+            }
+            sprite_index += 1;
+        }
+
+        //Once secondary OAM is full the PPU keeps looking for a ninth sprite,
+        //but it also increments the byte index within each sprite so it will
+        //compare tile indexes and attributes as if they were y coordinates.
+        let mut byte = 0;
+        for overflow_index in sprite_index..64 {
+            if is_on_scanline(self.sprites[overflow_index][byte], scanline, height) {
+                self.status_register |= 0x20;
+                break;
+            }
+            byte = (byte + 1) & 0x03;
+        }
+
+        if !self.sprite_limit {
+            for sprite_index in sprite_index..64 {
+                let position_y = (&self.sprites[sprite_index]).position_y();
+                if is_on_scanline(position_y, scanline, height) {
+                    self.fetch_sprite(sprite_index, scanline);
+                }
+            }
+        }
+    }
+
+    fn fetch_sprite(&mut self, sprite_index: usize, scanline: u32) {
+        if sprite_index == 0 {
+            self.sprite_zero_on_scanline = true;
+        }
+        let sprite = &self.sprites[sprite_index];
+        let height = self.control_register.sprite_height();
+        let row = scanline - sprite.position_y() as u32;
+        let row = if sprite.flip_vertical() {
+            height - 1 - row
+        } else {
+            row
         };
-        use std::cmp::min;
-        screen.set_backdrop_color(COLOUR_PALETTE[self.memory.get(0x3F00, 0) as usize]);
-        self.sprites.render_back_sprites(screen);
-        let area_width_minus_left = area_width.wrapping_sub(left);
-        let area_height_minus_top = area_height.wrapping_sub(top);
-        screen.render(
-            Rectangle {
-                x: left as i32,
-                y: top as i32,
-                width: min(screen_width, area_width_minus_left) as u32,
-                height: min(screen_height, area_height_minus_top) as u32,
-            },
-            0,
-            0,
-        );
-        //Do we need to patch to the right?
-        if area_width_minus_left < screen_width {
-            screen.render(
-                Rectangle {
-                    x: 0,
-                    y: top as i32,
-                    width: (screen_width - area_width_minus_left) as u32,
-                    height: min(screen_height, area_height_minus_top) as u32,
-                },
-                area_width - left,
-                0,
-            );
-        }
-        //Do we need to patch at the bottom?
-        if area_height_minus_top < screen_height {
-            screen.render(
-                Rectangle {
-                    x: left as i32,
-                    y: 0,
-                    width: min(screen_width, area_width_minus_left) as u32,
-                    height: (screen_height - area_height_minus_top) as u32,
-                },
-                0,
-                area_height - top as usize,
-            );
-        }
-        //Do we need to patch at the bottom right?
-        if area_width_minus_left < screen_width && area_height_minus_top < screen_height {
-            screen.render(
-                Rectangle {
-                    x: 0,
-                    y: 0,
-                    width: (screen_width - area_width_minus_left) as u32,
-                    height: (screen_height - area_height_minus_top) as u32,
-                },
-                area_width - left as usize,
-                area_height - top as usize,
-            );
-        }
-
-        //Update sprites
-        self.sprites.render_front_sprites(screen);
-
-        screen.present();
-    }
-
-    fn update_sprites(&mut self, buffer: &mut PixelBuffer) {
-        for sprite_index in 0..64 {
-            let sprite = &self.sprites[sprite_index];
-            let pattern_table_base_address = self.control_register.sprite_pattern_table();
-            let pattern_table_address =
-                pattern_table_base_address | ((sprite.pattern_index() as u16) << 4);
-            let pattern = self.memory.patterns()[(pattern_table_address as usize) >> 4];
-            pattern.update_buffer(
-                buffer,
-                self.memory.sprite_palette(sprite.colour_palette()),
-                sprite_index * 8,
-                0,
-            );
-        }
-    }
-
-    pub fn draw_buffer(&mut self, pixel_buffer: &mut PixelBuffer) {
-        let pattern_table = self.control_register.background_pattern_table() as usize;
-        let patterns = &self.memory.patterns()[pattern_table..(pattern_table + 0x100)];
-        let palettes = self.memory.background_palette();
-        let name_table = self.memory.name_table();
-        match self.mirroring {
-            ppumemory::Mirroring::Horizontal => {
-                name_table.update_tile_for_nametable(pixel_buffer, 0, patterns, palettes);
-                name_table.update_tile_for_nametable(pixel_buffer, 2, patterns, palettes);
+        let address = if height == 16 {
+            //8x16 sprites take the pattern table from bit 0 of the tile index
+            //and use the tiles 2n (top) and 2n+1 (bottom).
+            let pattern_table = ((sprite.pattern_index() & 0x01) as u16) << 12;
+            let tile = (sprite.pattern_index() & 0xFE) as u16 + (row as u16 >> 3);
+            pattern_table | (tile << 4) | (row as u16 & 0x07)
+        } else {
+            self.control_register.sprite_pattern_table()
+                | ((sprite.pattern_index() as u16) << 4)
+                | row as u16
+        };
+        let (pattern_low, pattern_high) =
+            (self.memory.get(address, 0), self.memory.get(address + 8, 0));
+        self.scanline_sprites.push(if sprite.flip_horizontal() {
+            ScanlineSprite {
+                x: sprite.position_x(),
+                pattern_low: pattern_low.reverse_bits(),
+                pattern_high: pattern_high.reverse_bits(),
+                attributes: sprite[2],
+            }
+        } else {
+            ScanlineSprite {
+                x: sprite.position_x(),
+                pattern_low: pattern_low,
+                pattern_high: pattern_high,
+                attributes: sprite[2],
             }
-            ppumemory::Mirroring::Vertical => {
-                name_table.update_tile_for_nametable(pixel_buffer, 0, patterns, palettes);
-                name_table.update_tile_for_nametable(pixel_buffer, 1, patterns, palettes);
+        });
+    }
+
+    /**
+     * Removes the limit of 8 sprites per scanline to get rid of flickering.
+     * The sprite overflow flag is still set as on the real hardware.
+     */
+    pub fn set_sprite_limit(&mut self, enabled: bool) {
+        self.sprite_limit = enabled;
+    }
+
+    pub fn set_palette(&mut self, palette: ColourPalette) {
+        self.palette = palette;
+    }
+
+    pub fn palette(&self) -> &ColourPalette {
+        &self.palette
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    fn draw_pixel(&mut self, scanline: usize, x: u8) {
+        let mask = &self.mask_register;
+        let background = if mask.show_background() && (x >= 8 || mask.show_background_left()) {
+            self.background.pixel(self.vram_registers.fine_x())
+        } else {
+            0
+        };
+        let sprite = if mask.show_sprites() && (x >= 8 || mask.show_sprites_left()) {
+            self.scanline_sprites
+                .iter()
+                .enumerate()
+                .map(|(index, sprite)| (index, sprite, sprite.colour(x)))
+                .find(|&(_, _, colour)| colour != 0)
+        } else {
+            None
+        };
+        //Disabled or clipped layers never hit, apart from that only x=255 is excluded
+        if let Some((0, _, _)) = sprite {
+            if self.sprite_zero_on_scanline && background != 0 && x != 255 {
+                self.status_register |= 0x40;
             }
-            ppumemory::Mirroring::NoMirroring => {
-                name_table.update_tile_for_nametable(pixel_buffer, 0, patterns, palettes);
-                name_table.update_tile_for_nametable(pixel_buffer, 1, patterns, palettes);
-                name_table.update_tile_for_nametable(pixel_buffer, 2, patterns, palettes);
-                name_table.update_tile_for_nametable(pixel_buffer, 3, patterns, palettes);
+        }
+        let palette_address = match sprite {
+            Some((_, sprite, colour)) if background == 0 || !sprite.is_back() => {
+                0x3F10 | ((sprite.colour_palette() as u16) << 2) | colour as u16
             }
+            _ => 0x3F00 | background as u16,
+        };
+        self.frame[scanline * SCREEN_WIDTH + x as usize] = self.output_colour(palette_address);
+    }
+
+    /**
+     * Index into the colour palette, including emphasis, of the given palette entry.
+     */
+    fn output_colour(&self, palette_address: u16) -> u16 {
+        let colour = self.memory.get(palette_address, 0) & self.mask_register.colour_mask();
+        let emphasis = self.mask_register.emphasis();
+        let emphasis = if self.region.swaps_red_and_green_emphasis() {
+            (emphasis & 0x04) | ((emphasis & 0x01) << 1) | ((emphasis & 0x02) >> 1)
+        } else {
+            emphasis
         };
+        (emphasis << 6) | colour as u16
     }
 
-    pub fn invalidate_tile_cache(&mut self) {
-        self.vram_changed = true;
-        self.memory.name_table_mut().invalidate_tile_cache();
+    /**
+     * Renders a complete frame at once from the current registers, starting
+     * from the scroll in t, and presents it. This changes v, the shift
+     * registers and the sprite state as rendering a frame does, so it is meant
+     * for setting up a picture between frames rather than while the PPU is
+     * being clocked.
+     */
+    pub fn update_screen<T>(&mut self, screen: &mut T)
+    where
+        T: Screen + Sized,
+    {
+        self.vram_registers.copy_temporary_bits();
+        for dot in 257..PPU_CYCLES_PER_SCANLINE {
+            let pre_render_scanline = self.pre_render_scanline();
+            self.render_dot(pre_render_scanline, dot);
+        }
+        for scanline in 0..VISIBLE_SCANLINES {
+            for dot in 0..PPU_CYCLES_PER_SCANLINE {
+                self.render_dot(scanline, dot);
+            }
+        }
+        self.present(screen);
+    }
+
+    /**
+     * Hands the last rendered frame to the screen.
+     */
+    pub fn present<T>(&self, screen: &mut T)
+    where
+        T: Screen + Sized,
+    {
+        screen.present(&self.frame());
+    }
+
+    /**
+     * The last rendered frame, or the one being rendered if called mid-frame.
+     */
+    pub fn frame(&self) -> Frame<'_> {
+        Frame::new(&self.frame[..], &self.palette)
     }
 
     pub fn memory(&self) -> &dyn Memory {
@@ -461,11 +966,17 @@ impl PPU {
     }
 }
 
+fn is_on_scanline(position_y: u8, scanline: u32, height: u32) -> bool {
+    scanline.wrapping_sub(position_y as u32) < height
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::{PPUStatus, PPU};
+    use ppu::events::{Event, EventKind};
     use ppu::ppumemory::PPUMemory;
     use ppu::screen::ScreenMock;
+    use region::Region;
 
     #[test]
     fn reading_status_register_should_clear_vblank() {
@@ -652,6 +1163,32 @@ pub mod tests {
         assert_eq!(true, ppu.status_register.is_vblank());
     }
 
+    #[test]
+    fn odd_frame_skip_should_not_depend_on_how_the_ppu_is_clocked() {
+        let background_ppu = || {
+            let mut ppu = PPU::new(PPUMemory::no_mirroring());
+            ppu.load(0x3F00, &[0x01, 0x16, 0x2A, 0x12]);
+            ppu.load(0x0000, &[0b1011_0010]); //Row 0 of background tile 0
+            ppu.load(0x0008, &[0b0110_1001]);
+            ppu.set_ppu_mask(0x0A, 0);
+            ppu
+        };
+        //Writing the mask already caught up 3 dots
+        let mut dot_by_dot = background_ppu();
+        for _ in 0..(89_341 + 341 - 3) {
+            dot_by_dot.update(1);
+        }
+
+        let mut batched = background_ppu();
+        batched.update(89_338 - 3); //Pre-render dot 337
+        batched.update(10); //Across the end of the odd frame to dot 7 of scanline 0
+        batched.update(334);
+
+        assert_eq!(341, dot_by_dot.cycle_count);
+        assert_eq!(341, batched.cycle_count);
+        assert_eq!(dot_by_dot.frame[0..256], batched.frame[0..256]);
+    }
+
     #[test]
     fn odd_frames_should_not_skip_one_cycle_if_rendering_is_disabled() {
         let mut ppu = PPU::new(PPUMemory::no_mirroring());
@@ -663,4 +1200,407 @@ pub mod tests {
         update_ppu(29780, &mut ppu); //82_180
         assert_eq!(false, ppu.status_register.is_vblank());
     }
+
+    #[test]
+    fn event_log_should_record_register_writes_and_nmi_per_frame() {
+        let screen = &mut ScreenMock::new();
+        let mut ppu = PPU::new(PPUMemory::no_mirroring());
+        ppu.set_ppu_ctrl(0x80);
+        ppu.set_event_logging(true);
+
+        update_ppu(100, &mut ppu);
+        ppu.set_program_counter(0xC010);
+        ppu.log_register_write(0x2005, 0x20, 0);
+        let write = Event {
+            scanline: 0,
+            dot: 303,
+            kind: EventKind::RegisterWrite(0x2005),
+            value: 0x20,
+            program_counter: 0xC010,
+        };
+        assert_eq!(&[write], ppu.events().unwrap().current_frame());
+        assert_eq!(300, ppu.dot());
+
+        while !ppu.sync(1, screen) {}
+        let nmi = ppu.events().unwrap().current_frame()[1];
+        assert_eq!(EventKind::Nmi, nmi.kind);
+        assert_eq!(241, nmi.scanline);
+
+        while ppu.frame_count() == 0 {
+            ppu.sync(1, screen);
+        }
+        assert_eq!(&[write, nmi], ppu.events().unwrap().last_frame());
+        assert!(ppu.events().unwrap().current_frame().is_empty());
+    }
+
+    #[test]
+    fn pal_vblank_should_start_after_3_2_ppu_cycles_per_cpu_cycle() {
+        let mut ppu = PPU::new(PPUMemory::no_mirroring());
+        ppu.set_region(Region::PAL);
+
+        update_ppu(25681, &mut ppu); //82_179.2
+        assert_eq!(false, ppu.status_register.is_vblank());
+        update_ppu(1, &mut ppu); //82_182.4
+        assert_eq!(true, ppu.status_register.is_vblank());
+
+        update_ppu(33141 - 25682, &mut ppu); //106_051.2
+        assert_eq!(true, ppu.status_register.is_vblank());
+        update_ppu(1, &mut ppu); //106_054.4 (pre-render scanline 311)
+        assert_eq!(false, ppu.status_register.is_vblank());
+    }
+
+    #[test]
+    fn pal_frames_should_not_skip_a_cycle_on_odd_frames() {
+        let mut ppu = PPU::new(PPUMemory::no_mirroring());
+        ppu.set_region(Region::PAL);
+
+        ppu.set_ppu_mask(0x18, 0);
+        update_ppu(25682, &mut ppu); //82_182.4
+        assert_eq!(true, ppu.status_register.is_vblank());
+
+        update_ppu(58929 - 25682, &mut ppu); //188_572.8, vblank of the second frame at 188_574
+        assert_eq!(false, ppu.status_register.is_vblank());
+        update_ppu(1, &mut ppu);
+        assert_eq!(true, ppu.status_register.is_vblank());
+    }
+
+    #[test]
+    fn pal_should_swap_red_and_green_emphasis() {
+        let mut ppu = PPU::new(PPUMemory::no_mirroring());
+        ppu.load(0x3F00, &[0x16]);
+        ppu.set_ppu_mask(0x20, 0);
+        assert_eq!(0x01 << 6 | 0x16, ppu.output_colour(0x3F00));
+
+        ppu.set_region(Region::PAL);
+        assert_eq!(0x02 << 6 | 0x16, ppu.output_colour(0x3F00));
+        ppu.set_ppu_mask(0xC0, 0);
+        assert_eq!(0x05 << 6 | 0x16, ppu.output_colour(0x3F00));
+    }
+
+    #[test]
+    fn palette_changes_should_take_effect_mid_frame() {
+        let screen = &mut ScreenMock::new();
+        let mut ppu = PPU::new(PPUMemory::no_mirroring());
+        ppu.load(0x3F00, &[0x01]);
+        ppu.set_ppu_mask(0x18, 0);
+
+        ppu.sync(13_640, screen); //40_920 = scanline 120
+        ppu.load(0x3F00, &[0x02]);
+        ppu.sync(29_780 - 13_640, screen); //89_340, before the next frame starts
+
+        assert_eq!(0x01, ppu.frame[0]);
+        assert_eq!(0x01, ppu.frame[119 * 256 + 255]);
+        assert_eq!(0x02, ppu.frame[120 * 256]);
+        assert_eq!(0x02, ppu.frame[239 * 256 + 255]);
+    }
+
+    #[test]
+    fn scroll_and_address_writes_should_catch_up_with_the_cpu() {
+        let screen = &mut ScreenMock::new();
+        let mut ppu = PPU::new(PPUMemory::no_mirroring());
+        ppu.sync(100, screen); //300
+
+        ppu.set_scroll_at_cycle(0x08, 1); //306
+        assert_eq!(306, ppu.cycle_count);
+        ppu.set_vram_at_cycle(0x20, 3); //312
+        assert_eq!(312, ppu.cycle_count);
+        ppu.set_vram_at_cycle(0x00, 3); //Same cycle, nothing more to execute
+        assert_eq!(312, ppu.cycle_count);
+
+        ppu.sync(4, screen); //312
+        assert_eq!(312, ppu.cycle_count);
+        ppu.sync(1, screen); //315
+        assert_eq!(315, ppu.cycle_count);
+    }
+
+    fn ppu_with_sprites(sprites: &[(usize, [u8; 4])]) -> PPU {
+        let mut ppu = PPU::new(PPUMemory::no_mirroring());
+        for byte in ppu.sprites_mut().slice().iter_mut() {
+            *byte = 0xFF;
+        }
+        for &(index, sprite) in sprites {
+            ppu.sprites_mut().slice()[index * 4..index * 4 + 4].copy_from_slice(&sprite);
+        }
+        ppu
+    }
+
+    #[test]
+    fn at_most_8_sprites_should_be_drawn_on_a_scanline() {
+        let sprites: Vec<(usize, [u8; 4])> = (0..9).map(|i| (i, [10, 0, 0, 0])).collect();
+        let mut ppu = ppu_with_sprites(&sprites);
+
+        ppu.evaluate_sprites(10);
+        assert_eq!(8, ppu.scanline_sprites.len());
+        assert_eq!(0x20, ppu.status_register & 0x20);
+    }
+
+    #[test]
+    fn exactly_8_sprites_should_not_overflow() {
+        let sprites: Vec<(usize, [u8; 4])> = (0..8).map(|i| (i, [10, 0, 0, 0])).collect();
+        let mut ppu = ppu_with_sprites(&sprites);
+
+        ppu.evaluate_sprites(17);
+        assert_eq!(8, ppu.scanline_sprites.len());
+        assert_eq!(0x00, ppu.status_register & 0x20);
+
+        ppu.evaluate_sprites(18);
+        assert_eq!(0, ppu.scanline_sprites.len());
+        assert_eq!(0x00, ppu.status_register & 0x20);
+    }
+
+    #[test]
+    fn sprite_overflow_should_compare_the_wrong_bytes_after_8_sprites() {
+        let mut sprites: Vec<(usize, [u8; 4])> = (0..8).map(|i| (i, [10, 0, 0, 0])).collect();
+        //False positive, the tile index of sprite 9 is compared
+        sprites.push((9, [0xFF, 5, 0xFF, 0xFF]));
+        let mut ppu = ppu_with_sprites(&sprites);
+        ppu.evaluate_sprites(10);
+        assert_eq!(0x20, ppu.status_register & 0x20);
+
+        //False negative, the tile index of sprite 9 is compared instead of y
+        sprites.pop();
+        sprites.push((9, [10, 0xFF, 0xFF, 0xFF]));
+        let mut ppu = ppu_with_sprites(&sprites);
+        ppu.evaluate_sprites(10);
+        assert_eq!(0x00, ppu.status_register & 0x20);
+    }
+
+    #[test]
+    fn all_sprites_should_be_drawn_without_the_sprite_limit() {
+        let sprites: Vec<(usize, [u8; 4])> = (0..12).map(|i| (i, [10, 0, 0, i as u8])).collect();
+        let mut ppu = ppu_with_sprites(&sprites);
+        ppu.set_sprite_limit(false);
+
+        ppu.evaluate_sprites(10);
+        assert_eq!(12, ppu.scanline_sprites.len());
+        assert_eq!(11, ppu.scanline_sprites[11].x);
+        assert_eq!(0x20, ppu.status_register & 0x20);
+    }
+
+    #[test]
+    fn sprites_should_be_16_pixels_high_in_8x16_mode() {
+        let mut ppu = ppu_with_sprites(&[(0, [10, 0, 0, 0])]);
+
+        ppu.evaluate_sprites(25);
+        assert_eq!(0, ppu.scanline_sprites.len());
+
+        ppu.set_ppu_ctrl(0x20);
+        ppu.evaluate_sprites(25);
+        assert_eq!(1, ppu.scanline_sprites.len());
+        ppu.evaluate_sprites(26);
+        assert_eq!(0, ppu.scanline_sprites.len());
+    }
+
+    #[test]
+    fn sprite_pattern_table_should_be_selected_by_the_tile_index_in_8x16_mode() {
+        let mut ppu = ppu_with_sprites(&[(0, [10, 0x03, 0, 0])]);
+        ppu.set_ppu_ctrl(0x20);
+        //Tile 2 and 3 in the second pattern table, the low plane of each row
+        //holds the row number within the sprite.
+        for row in 0..8 {
+            ppu.load(0x1020 + row, &[row as u8]);
+            ppu.load(0x1030 + row, &[8 + row as u8]);
+        }
+
+        let rows: Vec<u8> = (10..26)
+            .map(|scanline| {
+                ppu.evaluate_sprites(scanline);
+                ppu.scanline_sprites[0].pattern_low
+            })
+            .collect();
+        assert_eq!((0..16).collect::<Vec<u8>>(), rows);
+    }
+
+    #[test]
+    fn vertical_flip_should_swap_the_halves_of_8x16_sprites() {
+        let mut ppu = ppu_with_sprites(&[(0, [10, 0x02, 0x80, 0])]);
+        ppu.set_ppu_ctrl(0x20);
+        for row in 0..8 {
+            ppu.load(0x0020 + row, &[row as u8]);
+            ppu.load(0x0030 + row, &[8 + row as u8]);
+        }
+
+        let rows: Vec<u8> = (10..26)
+            .map(|scanline| {
+                ppu.evaluate_sprites(scanline);
+                ppu.scanline_sprites[0].pattern_low
+            })
+            .collect();
+        assert_eq!((0..16).rev().collect::<Vec<u8>>(), rows);
+    }
+
+    #[test]
+    fn ppu_data_access_while_rendering_should_increment_coarse_x_and_y() {
+        let mut ppu = PPU::new(PPUMemory::no_mirroring());
+        ppu.update(10 * 341 + 100);
+
+        ppu.vram_registers.current = 0x0000;
+        ppu.read_from_vram();
+        assert_eq!(0x0001, ppu.vram());
+
+        //Fine y is in bits 12-14 and coarse x in bits 0-4
+        ppu.set_ppu_mask(0x18, 0);
+        ppu.vram_registers.current = 0x0000;
+        ppu.read_from_vram();
+        assert_eq!(0x1001, ppu.vram());
+        ppu.write_to_vram(0x00);
+        assert_eq!(0x2002, ppu.vram());
+
+        //Not during vblank
+        ppu.update(240 * 341);
+        ppu.vram_registers.current = 0x0000;
+        ppu.read_from_vram();
+        assert_eq!(0x0001, ppu.vram());
+    }
+
+    #[test]
+    fn palette_reads_should_take_the_upper_bits_from_the_io_latch() {
+        let mut ppu = PPU::new(PPUMemory::no_mirroring());
+        ppu.load(0x3F01, &[0x2A]);
+        ppu.set_io_latch(0xFF);
+        ppu.vram_registers.current = 0x3F01;
+        assert_eq!(0xEA, ppu.read_from_vram());
+
+        ppu.set_ppu_mask(0x01, 0);
+        ppu.set_io_latch(0x00);
+        ppu.vram_registers.current = 0x3F01;
+        assert_eq!(0x20, ppu.read_from_vram());
+    }
+
+    #[test]
+    fn io_latch_bits_should_decay_unless_refreshed() {
+        let mut ppu = PPU::new(PPUMemory::no_mirroring());
+        ppu.set_io_latch(0xFF);
+
+        ppu.frame_count = 20;
+        ppu.status_register = 0xE0;
+        assert_eq!(0xFF, ppu.status(0));
+
+        ppu.frame_count = 40;
+        assert_eq!(0xE0, ppu.io_latch());
+        ppu.frame_count = 60;
+        assert_eq!(0x00, ppu.io_latch());
+    }
+
+    #[test]
+    fn oam_data_reads_while_rendering_should_return_the_evaluation_bus() {
+        let mut ppu = ppu_with_sprites(&[(0, [10, 0x22, 0xFF, 0x33]), (1, [0x44, 0, 0, 0])]);
+        ppu.sprites_mut().set_address(1);
+        ppu.set_ppu_mask(0x18, 0);
+
+        //Writing the mask already caught up 3 dots
+        ppu.update(10 * 341 + 30 - 3);
+        assert_eq!(0xFF, ppu.read_oam_data(0));
+        ppu.update(66 - 30);
+        assert_eq!(66, ppu.dot());
+        assert_eq!(10, ppu.oam_bus());
+        ppu.update(2);
+        assert_eq!(0x44, ppu.oam_bus());
+
+        ppu.update(258 - 68);
+        assert_eq!(0x22, ppu.oam_bus());
+        ppu.update(1);
+        assert_eq!(0xE3, ppu.oam_bus());
+        ppu.update(1);
+        assert_eq!(0x33, ppu.oam_bus());
+        ppu.update(5);
+        assert_eq!(0xFF, ppu.oam_bus());
+
+        ppu.set_ppu_mask(0x00, 0);
+        assert_eq!(0x22, ppu.read_oam_data(0));
+    }
+
+    #[test]
+    fn oam_data_read_at_dot_0_should_return_the_first_byte_of_secondary_oam() {
+        let mut ppu = ppu_with_sprites(&[(0, [10, 0x22, 0xFF, 0x33])]);
+        ppu.set_ppu_mask(0x18, 0);
+
+        //Writing the mask already caught up 3 dots
+        ppu.update(11 * 341 - 3);
+        assert_eq!(10, ppu.read_oam_data(0));
+        assert_eq!(0, ppu.dot());
+    }
+
+    fn render_first_pixels(mask: u8) -> PPU {
+        let screen = &mut ScreenMock::new();
+        let mut ppu = ppu_with_sprites(&[(0, [0, 0x01, 0x01, 4])]);
+        ppu.load(0x3F00, &[0x01, 0x16, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        ppu.load(0x3F10, &[0x01, 0x00, 0x00, 0x00, 0x00, 0x2A]);
+        ppu.load(0x0000, &[0xFF]); //Row 0 of background tile 0
+        ppu.load(0x0010, &[0xFF]); //Row 0 of sprite tile 1
+        ppu.set_ppu_mask(mask, 0);
+        ppu.update_screen(screen);
+        ppu
+    }
+
+    fn second_row(ppu: &PPU) -> Vec<u16> {
+        ppu.frame[256..256 + 10].to_vec()
+    }
+
+    #[test]
+    fn background_and_sprites_should_be_enabled_separately() {
+        let ppu = render_first_pixels(0x1E);
+        assert_eq!(vec![0x16; 4], ppu.frame[0..4].to_vec());
+        assert_eq!(vec![0x01; 4], second_row(&ppu)[0..4].to_vec());
+        assert_eq!(vec![0x2A; 6], second_row(&ppu)[4..10].to_vec());
+
+        let ppu = render_first_pixels(0x0E);
+        assert_eq!(vec![0x16; 4], ppu.frame[0..4].to_vec());
+        assert_eq!(vec![0x01; 10], second_row(&ppu));
+
+        let ppu = render_first_pixels(0x16);
+        assert_eq!(vec![0x01; 4], ppu.frame[0..4].to_vec());
+        assert_eq!(vec![0x2A; 6], second_row(&ppu)[4..10].to_vec());
+    }
+
+    #[test]
+    fn left_most_8_pixels_should_be_clipped() {
+        let ppu = render_first_pixels(0x1C);
+        assert_eq!(vec![0x01; 8], ppu.frame[0..8].to_vec());
+        assert_eq!(0x16, ppu.frame[8]);
+        assert_eq!(vec![0x2A; 6], second_row(&ppu)[4..10].to_vec());
+
+        let ppu = render_first_pixels(0x1A);
+        assert_eq!(vec![0x16; 8], ppu.frame[0..8].to_vec());
+        assert_eq!(vec![0x01; 4], second_row(&ppu)[4..8].to_vec());
+        assert_eq!(vec![0x2A; 2], second_row(&ppu)[8..10].to_vec());
+    }
+
+    #[test]
+    fn greyscale_and_emphasis_should_apply_to_all_pixels() {
+        let ppu = render_first_pixels(0x1F);
+        assert_eq!(0x10, ppu.frame[0]);
+        assert_eq!(0x00, ppu.frame[256]);
+        assert_eq!(0x20, ppu.frame[256 + 4]);
+
+        let ppu = render_first_pixels(0xBE);
+        assert_eq!(0x05 << 6 | 0x16, ppu.frame[0]);
+        assert_eq!(0x05 << 6 | 0x01, ppu.frame[256]);
+        assert_eq!(0x05 << 6 | 0x2A, ppu.frame[256 + 4]);
+
+        //Also when rendering is disabled
+        let mut ppu = render_first_pixels(0x41);
+        let screen = &mut ScreenMock::new();
+        ppu.sync(10, screen);
+        assert_eq!(0x02 << 6 | 0x00, ppu.frame[0]);
+    }
+
+    #[test]
+    fn writes_should_be_ignored_until_the_end_of_vblank_after_reset() {
+        let screen = &mut ScreenMock::new();
+        let mut ppu = PPU::new(PPUMemory::no_mirroring());
+        ppu.reset();
+
+        ppu.set_vram(0x21);
+        ppu.set_vram(0x08);
+        assert_eq!(0x0000, ppu.vram());
+
+        ppu.sync(29_665, screen); //88_995
+        ppu.set_ppu_ctrl(0x80); //88_998
+        assert_eq!(0x00, ppu.ppu_ctrl());
+
+        ppu.sync(2, screen); //89_001
+        ppu.set_ppu_ctrl(0x80); //89_004
+        assert_eq!(0x80, ppu.ppu_ctrl());
+    }
 }
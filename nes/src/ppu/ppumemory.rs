@@ -76,14 +76,6 @@ impl PPUMemory {
         &self.palettes[(index + 4) as usize]
     }
 
-    pub fn name_table(&self) -> &NameTable {
-        &self.name_tables
-    }
-
-    pub fn name_table_mut(&mut self) -> &mut NameTable {
-        &mut self.name_tables
-    }
-
     fn translate(&self, address: Address) -> Address {
         if address >= 0x2000 && address < 0x3000 {
             address & self.name_table_mirror_mask
@@ -16,35 +16,52 @@ pub struct OAMDMA(pub Rc<RefCell<PPU>>);
 
 impl MemoryMappedIO for PPUCtrl {
     fn read(&self, _: &dyn Memory) -> u8 {
-        self.0.borrow().ppu_ctrl()
+        self.0.borrow().io_latch()
     }
     fn write(&mut self, _: &mut dyn Memory, value: u8) {
-        self.0.borrow_mut().set_ppu_ctrl_at_cycle(value, 0);
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2000, value, 0);
+        ppu.set_io_latch(value);
+        ppu.set_ppu_ctrl_at_cycle(value, 0);
     }
 
     fn write_at_cycle(&mut self, _: &mut dyn Memory, value: u8, sub_cycle: u8) {
-        self.0.borrow_mut().set_ppu_ctrl_at_cycle(value, sub_cycle);
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2000, value, sub_cycle);
+        ppu.set_io_latch(value);
+        ppu.set_ppu_ctrl_at_cycle(value, sub_cycle);
     }
 }
 
 impl MemoryMappedIO for PPUMask {
     fn read(&self, _: &dyn Memory) -> u8 {
-        unimplemented!();
+        self.0.borrow().io_latch()
     }
     fn write(&mut self, _: &mut dyn Memory, value: u8) {
-        self.0.borrow_mut().set_ppu_mask(value, 0);
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2001, value, 0);
+        ppu.set_io_latch(value);
+        ppu.set_ppu_mask(value, 0);
     }
 
     fn write_at_cycle(&mut self, _: &mut dyn Memory, value: u8, sub_cycle: u8) {
-        self.0.borrow_mut().set_ppu_mask(value, sub_cycle);
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2001, value, sub_cycle);
+        ppu.set_io_latch(value);
+        ppu.set_ppu_mask(value, sub_cycle);
     }
 }
 impl MemoryMappedIO for PPUStatus {
     fn read(&self, _: &dyn Memory) -> u8 {
         self.0.borrow_mut().status(0)
     }
-    fn write(&mut self, _: &mut dyn Memory, _: u8) {
-        //Do nothing
+    fn write(&mut self, memory: &mut dyn Memory, value: u8) {
+        self.write_at_cycle(memory, value, 0);
+    }
+    fn write_at_cycle(&mut self, _: &mut dyn Memory, value: u8, sub_cycle: u8) {
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2002, value, sub_cycle);
+        ppu.set_io_latch(value);
     }
     fn read_at_cycle(&self, _: &dyn Memory, sub_cycle: u8) -> u8 {
         self.0.borrow_mut().status(sub_cycle)
@@ -52,34 +69,71 @@ impl MemoryMappedIO for PPUStatus {
 }
 impl MemoryMappedIO for PPUScroll {
     fn read(&self, _: &dyn Memory) -> u8 {
-        unimplemented!();
+        self.0.borrow().io_latch()
     }
     fn write(&mut self, _: &mut dyn Memory, value: u8) {
-        self.0.borrow_mut().set_scroll(value);
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2005, value, 0);
+        ppu.set_io_latch(value);
+        ppu.set_scroll(value);
+    }
+
+    fn write_at_cycle(&mut self, _: &mut dyn Memory, value: u8, sub_cycle: u8) {
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2005, value, sub_cycle);
+        ppu.set_io_latch(value);
+        ppu.set_scroll_at_cycle(value, sub_cycle);
     }
 }
 impl MemoryMappedIO for PPUAddress {
     fn read(&self, _: &dyn Memory) -> u8 {
-        unimplemented!();
+        self.0.borrow().io_latch()
     }
     fn write(&mut self, _: &mut dyn Memory, value: u8) {
-        self.0.borrow_mut().set_vram(value);
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2006, value, 0);
+        ppu.set_io_latch(value);
+        ppu.set_vram(value);
+    }
+
+    fn write_at_cycle(&mut self, _: &mut dyn Memory, value: u8, sub_cycle: u8) {
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2006, value, sub_cycle);
+        ppu.set_io_latch(value);
+        ppu.set_vram_at_cycle(value, sub_cycle);
     }
 }
 
 impl MemoryMappedIO for PPUData {
     fn read(&self, _: &dyn Memory) -> u8 {
-        self.0.borrow_mut().read_from_vram()
+        self.0.borrow_mut().read_from_vram_at_cycle(0)
     }
 
     fn write(&mut self, _: &mut dyn Memory, value: u8) {
-        self.0.borrow_mut().write_to_vram(value);
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2007, value, 0);
+        ppu.set_io_latch(value);
+        ppu.write_to_vram_at_cycle(value, 0);
+    }
+
+    fn read_at_cycle(&self, _: &dyn Memory, sub_cycle: u8) -> u8 {
+        self.0.borrow_mut().read_from_vram_at_cycle(sub_cycle)
+    }
+
+    fn write_at_cycle(&mut self, _: &mut dyn Memory, value: u8, sub_cycle: u8) {
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2007, value, sub_cycle);
+        ppu.set_io_latch(value);
+        ppu.write_to_vram_at_cycle(value, sub_cycle);
     }
 }
 
 impl MemoryMappedIO for OAMDMA {
     fn read(&self, _: &dyn Memory) -> u8 {
-        unimplemented!();
+        0
+    }
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
     }
     fn write(&mut self, memory: &mut dyn Memory, value: u8) {
         let dma_address: u16 = (value as u16) << 8;
@@ -104,25 +158,40 @@ impl MemoryMappedIO for OAMDMA {
 
 impl MemoryMappedIO for OAMAddress {
     fn read(&self, _: &dyn Memory) -> u8 {
-        unimplemented!();
+        self.0.borrow().io_latch()
     }
-    fn write(&mut self, _: &mut dyn Memory, value: u8) {
-        self.0.borrow_mut().sprites_mut().set_address(value);
+    fn write(&mut self, memory: &mut dyn Memory, value: u8) {
+        self.write_at_cycle(memory, value, 0);
+    }
+    fn write_at_cycle(&mut self, _: &mut dyn Memory, value: u8, sub_cycle: u8) {
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2003, value, sub_cycle);
+        ppu.set_io_latch(value);
+        ppu.sprites_mut().set_address(value);
     }
 }
 
 impl MemoryMappedIO for OAMData {
     fn read(&self, _: &dyn Memory) -> u8 {
-        self.0.borrow().sprites().read_byte()
+        self.0.borrow_mut().read_oam_data(0)
     }
-    fn write(&mut self, _: &mut dyn Memory, value: u8) {
-        self.0.borrow_mut().sprites_mut().write_byte(value);
+    fn read_at_cycle(&self, _: &dyn Memory, sub_cycle: u8) -> u8 {
+        self.0.borrow_mut().read_oam_data(sub_cycle)
+    }
+    fn write(&mut self, memory: &mut dyn Memory, value: u8) {
+        self.write_at_cycle(memory, value, 0);
+    }
+    fn write_at_cycle(&mut self, _: &mut dyn Memory, value: u8, sub_cycle: u8) {
+        let mut ppu = self.0.borrow_mut();
+        ppu.log_register_write(0x2004, value, sub_cycle);
+        ppu.set_io_latch(value);
+        ppu.sprites_mut().write_byte(value);
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{OAMAddress, OAMData, PPUAddress, PPUData, OAMDMA};
+    use super::{OAMAddress, OAMData, PPUAddress, PPUData, PPUMask, PPUStatus, OAMDMA};
     use memory::BasicMemory;
     use memory::Memory;
     use ppu::ppumemory::PPUMemory;
@@ -269,6 +338,34 @@ mod test {
         assert_eq!(0x34, memory.get(0x2004, 0));
     }
 
+    #[test]
+    fn logged_writes_should_be_placed_at_their_cycle_without_moving_the_ppu() {
+        let ppu = Rc::new(RefCell::new(PPU::new(PPUMemory::no_mirroring())));
+        ppu.borrow_mut().set_event_logging(true);
+        let basic_memory = BasicMemory::new();
+        let mut memory = cpu_memory!(
+            box basic_memory,
+            0x2002 => MutableRef::Box(box PPUStatus(ppu.clone())),
+            0x2003 => MutableRef::Box(box OAMAddress(ppu.clone())),
+            0x2004 => MutableRef::Box(box OAMData(ppu.clone()))
+        );
+
+        memory.set(0x2002, 0x01, 1);
+        memory.set(0x2003, 0x02, 2);
+        memory.set(0x2004, 0x03, 3);
+
+        let ppu = ppu.borrow();
+        let dots: Vec<u32> = ppu
+            .events()
+            .unwrap()
+            .current_frame()
+            .iter()
+            .map(|event| event.dot)
+            .collect();
+        assert_eq!(vec![6, 9, 12], dots);
+        assert_eq!(0, ppu.dot());
+    }
+
     #[test]
     fn oam_address_should_not_increase_on_read() {
         let ppu = Rc::new(RefCell::new(PPU::new(PPUMemory::no_mirroring())));
@@ -289,24 +386,80 @@ mod test {
     }
 
     #[test]
-    fn dma_copy_should_start_at_oam_address_and_wrap() {
+    fn unimplemented_attribute_bits_should_read_as_0() {
         let ppu = Rc::new(RefCell::new(PPU::new(PPUMemory::no_mirroring())));
-        let basic_memory = memory!(
-            0x0200 => 1,
-            0x0201 => 2,
-            0x0203 => 3,
-            0x0204 => 4
-        );
+        let basic_memory = BasicMemory::new();
         let mut memory = cpu_memory!(
             box basic_memory,
             0x2003 => MutableRef::Box(box OAMAddress(ppu.clone())),
+            0x2004 => MutableRef::Box(box OAMData(ppu.clone()))
+        );
+
+        memory.set(0x2003, 0x0, 0);
+        for _ in 0..4 {
+            memory.set(0x2004, 0xFF, 0);
+        }
+
+        let values: Vec<u8> = (0..4)
+            .map(|address| {
+                memory.set(0x2003, address, 0);
+                memory.get(0x2004, 0)
+            })
+            .collect();
+        assert_eq!(vec![0xFF, 0xFF, 0xE3, 0xFF], values);
+    }
+
+    #[test]
+    fn dma_copy_should_start_at_oam_address_and_wrap() {
+        let ppu = Rc::new(RefCell::new(PPU::new(PPUMemory::no_mirroring())));
+        let mut memory = cpu_memory!(
+            box BasicMemory::new(),
+            0x2003 => MutableRef::Box(box OAMAddress(ppu.clone())),
             0x2004 => MutableRef::Box(box OAMData(ppu.clone())),
             0x4014 => MutableRef::Box(box OAMDMA(ppu.clone()))
         );
+        memory.set(0x0200, 1, 0);
+        memory.set(0x0201, 2, 0);
+        memory.set(0x0203, 3, 0);
+        memory.set(0x0204, 4, 0);
 
         memory.set(0x2003, 0x1, 0);
         memory.set(0x4014, 0x02, 0);
         memory.set(0x2003, 0x1, 0);
         assert_eq!(1, memory.get(0x2004, 0));
     }
+
+    #[test]
+    fn reading_write_only_registers_should_return_the_io_latch() {
+        let ppu = Rc::new(RefCell::new(PPU::new(PPUMemory::no_mirroring())));
+        let basic_memory = BasicMemory::new();
+        let mut memory = cpu_memory!(
+            box basic_memory,
+            0x2001 => MutableRef::Box(box PPUMask(ppu.clone())),
+            0x2002 => MutableRef::Box(box PPUStatus(ppu.clone())),
+            0x2006 => MutableRef::Box(box PPUAddress(ppu.clone()))
+        );
+
+        memory.set(0x2001, 0x1E, 0);
+        assert_eq!(0x1E, memory.get(0x2006, 0));
+        assert_eq!(0x1E, memory.get(0x2001, 0));
+
+        memory.set(0x2006, 0x3F, 0);
+        assert_eq!(0x3F, memory.get(0x2001, 0));
+    }
+
+    #[test]
+    fn status_read_should_fill_low_bits_from_the_io_latch() {
+        let ppu = Rc::new(RefCell::new(PPU::new(PPUMemory::no_mirroring())));
+        let basic_memory = BasicMemory::new();
+        let mut memory = cpu_memory!(
+            box basic_memory,
+            0x2002 => MutableRef::Box(box PPUStatus(ppu.clone())),
+            0x2006 => MutableRef::Box(box PPUAddress(ppu.clone()))
+        );
+
+        memory.set(0x2006, 0xFF, 0);
+        assert_eq!(0x1F, memory.get(0x2002, 0));
+        assert_eq!(0x1F, memory.get(0x2006, 0));
+    }
 }
@@ -37,7 +37,42 @@ impl<'a> Sprite for &'a [u8] {
     }
 }
 
-use ppu::screen::{Rectangle, Screen};
+/**
+ * A sprite selected for a scanline, together with the row of its pattern that
+ * falls on that scanline. Horizontal flipping is already applied to the pattern.
+ */
+#[derive(Copy, Clone)]
+pub struct ScanlineSprite {
+    pub x: u8,
+    pub pattern_low: u8,
+    pub pattern_high: u8,
+    pub attributes: u8,
+}
+
+impl ScanlineSprite {
+    /**
+     * The colour (1-3) of this sprite at the given screen x, or 0 if the sprite
+     * is transparent or does not cover that pixel.
+     */
+    pub fn colour(&self, x: u8) -> u8 {
+        if x < self.x || x - self.x >= 8 {
+            return 0;
+        }
+        let bit = 7 - (x - self.x);
+        (((self.pattern_high >> bit) & 0x01) << 1) | ((self.pattern_low >> bit) & 0x01)
+    }
+
+    pub fn colour_palette(&self) -> u8 {
+        self.attributes & 0x3
+    }
+
+    pub fn is_back(&self) -> bool {
+        self.attributes & 0x20 != 0
+    }
+}
+
+pub const ATTRIBUTE_BITS: u8 = 0xE3;
+
 pub struct Sprites {
     data: [u8; 64 * 4],
     address: u8,
@@ -64,54 +99,15 @@ impl Sprites {
         self.data[address as usize] = value;
     }
 
+    /**
+     * Bits 2-4 of the attributes are not implemented in OAM and read back as 0.
+     */
     pub fn read_byte(&self) -> u8 {
-        self.data[self.address as usize]
-    }
-
-    pub fn render_back_sprites<T>(&self, screen: &mut T)
-    where
-        T: Screen + Sized,
-    {
-        for sprite_index in (0..64).rev() {
-            let sprite = &self.data[(sprite_index * 4)..(sprite_index * 4 + 4)];
-            let position_y = sprite.position_y();
-            if sprite.is_back() && position_y < 0xFE {
-                screen.render_sprite(
-                    Rectangle {
-                        x: (sprite_index * 8) as i32,
-                        y: 0,
-                        width: 8,
-                        height: 8,
-                    },
-                    sprite.position_x() as usize,
-                    (position_y + 1) as usize,
-                    sprite.flip_horizontal(),
-                    sprite.flip_vertical(),
-                );
-            }
-        }
-    }
-    pub fn render_front_sprites<T>(&self, screen: &mut T)
-    where
-        T: Screen + Sized,
-    {
-        for sprite_index in (0..64).rev() {
-            let sprite = &self.data[(sprite_index * 4)..(sprite_index * 4 + 4)];
-            let position_y = sprite.position_y();
-            if sprite.is_front() && position_y < 0xFE {
-                screen.render_sprite(
-                    Rectangle {
-                        x: (sprite_index * 8) as i32,
-                        y: 0,
-                        width: 8,
-                        height: 8,
-                    },
-                    sprite.position_x() as usize,
-                    (position_y + 1) as usize,
-                    sprite.flip_horizontal(),
-                    sprite.flip_vertical(),
-                );
-            }
+        let value = self.data[self.address as usize];
+        if self.address & 0x03 == 2 {
+            value & ATTRIBUTE_BITS
+        } else {
+            value
         }
     }
 
@@ -127,3 +123,24 @@ impl Index<usize> for Sprites {
         &self.data[(index * 4)..(index * 4 + 4)]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::ScanlineSprite;
+
+    #[test]
+    fn scanline_sprite_should_only_cover_eight_pixels() {
+        let sprite = ScanlineSprite {
+            x: 0xFC,
+            pattern_low: 0b1000_0001,
+            pattern_high: 0b1000_0000,
+            attributes: 0,
+        };
+        assert_eq!(0, sprite.colour(0xFB));
+        assert_eq!(3, sprite.colour(0xFC));
+        assert_eq!(0, sprite.colour(0xFD));
+        assert_eq!(0, sprite.colour(0xFF));
+        assert_eq!(0, sprite.colour(0x00));
+        assert_eq!(0, sprite.colour(0x03));
+    }
+}
@@ -0,0 +1,431 @@
+use ppu::attributetable::AttributeTable;
+use ppu::events::EventKind;
+use ppu::screen::{Color, SCREEN_HEIGHT, SCREEN_WIDTH};
+use ppu::sprite::Sprite;
+use ppu::PPU;
+use std::cmp::min;
+
+/**
+ * An RGBA image of some part of the PPU state, for debugging.
+ */
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+impl Image {
+    /**
+     * A fully transparent image.
+     */
+    pub fn new(width: usize, height: usize) -> Image {
+        Image {
+            width: width,
+            height: height,
+            rgba: vec![0; width * height * 4],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> (u8, u8, u8, u8) {
+        let offset = (y * self.width + x) * 4;
+        (
+            self.rgba[offset],
+            self.rgba[offset + 1],
+            self.rgba[offset + 2],
+            self.rgba[offset + 3],
+        )
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, colour: Color) {
+        let offset = (y * self.width + x) * 4;
+        self.rgba[offset] = colour.0;
+        self.rgba[offset + 1] = colour.1;
+        self.rgba[offset + 2] = colour.2;
+        self.rgba[offset + 3] = 255;
+    }
+}
+
+pub const NAME_TABLES_WIDTH: usize = 512;
+pub const NAME_TABLES_HEIGHT: usize = 480;
+pub const PATTERN_TABLES_WIDTH: usize = 256;
+pub const PATTERN_TABLES_HEIGHT: usize = 128;
+pub const PALETTE_WIDTH: usize = 16 * PALETTE_SWATCH_SIZE;
+pub const PALETTE_HEIGHT: usize = 2 * PALETTE_SWATCH_SIZE;
+//8 by 8 sprites, each in a 8x16 cell to fit 8x16 sprites
+pub const SPRITES_WIDTH: usize = 8 * 8;
+pub const SPRITES_HEIGHT: usize = 8 * 16;
+
+//One pixel per dot
+pub const EVENTS_WIDTH: usize = 341;
+
+const PALETTE_SWATCH_SIZE: usize = 16;
+//$2000-$2007
+const REGISTER_COLOURS: [Color; 8] = [
+    (255, 64, 64),
+    (64, 255, 64),
+    (255, 255, 64),
+    (64, 64, 255),
+    (255, 160, 0),
+    (64, 255, 255),
+    (160, 96, 255),
+    (255, 128, 192),
+];
+const SCROLL_WINDOW_COLOUR: Color = (255, 0, 0);
+
+/**
+ * All four name tables as laid out in the 512x480 scroll area, drawn with the
+ * current background pattern table and palettes. The part that will be shown
+ * in the next frame is outlined.
+ */
+pub fn name_tables(ppu: &PPU) -> Image {
+    let memory = ppu.memory();
+    let mut image = Image::new(NAME_TABLES_WIDTH, NAME_TABLES_HEIGHT);
+    for name_table in 0..4 {
+        let base_address = 0x2000 + name_table * 0x400;
+        let attribute_table = AttributeTable {
+            memory: memory,
+            address: base_address + 0x3C0,
+        };
+        let x_offset = (name_table as usize & 0x01) * 256;
+        let y_offset = (name_table as usize >> 1) * 240;
+        for row in 0..30 {
+            for col in 0..32 {
+                let tile = memory.get(base_address + row * 32 + col, 0);
+                draw_tile(
+                    &mut image,
+                    ppu,
+                    ppu.background_pattern_table() + tile as u16 * 16,
+                    attribute_table.get_palette_index(row, col),
+                    (x_offset + col as usize * 8, y_offset + row as usize * 8),
+                    (false, false),
+                    false,
+                );
+            }
+        }
+    }
+
+    let (scroll_x, scroll_y) = ppu.scroll();
+    let (scroll_x, scroll_y) = (scroll_x as usize, scroll_y as usize);
+    for x in 0..256 {
+        let x = (scroll_x + x) % NAME_TABLES_WIDTH;
+        image.set_pixel(x, scroll_y, SCROLL_WINDOW_COLOUR);
+        image.set_pixel(
+            x,
+            (scroll_y + 239) % NAME_TABLES_HEIGHT,
+            SCROLL_WINDOW_COLOUR,
+        );
+    }
+    for y in 0..240 {
+        let y = (scroll_y + y) % NAME_TABLES_HEIGHT;
+        image.set_pixel(scroll_x, y, SCROLL_WINDOW_COLOUR);
+        image.set_pixel(
+            (scroll_x + 255) % NAME_TABLES_WIDTH,
+            y,
+            SCROLL_WINDOW_COLOUR,
+        );
+    }
+    image
+}
+
+/**
+ * Both pattern tables side by side, 16x16 tiles each, coloured with one of
+ * the 8 palettes (0-3 background, 4-7 sprites).
+ */
+pub fn pattern_tables(ppu: &PPU, palette: u8) -> Image {
+    let mut image = Image::new(PATTERN_TABLES_WIDTH, PATTERN_TABLES_HEIGHT);
+    for table in 0..2 {
+        for tile in 0..256 {
+            draw_tile(
+                &mut image,
+                ppu,
+                table * 0x1000 + tile * 16,
+                palette & 0x07,
+                (
+                    table as usize * 128 + (tile as usize % 16) * 8,
+                    (tile as usize / 16) * 8,
+                ),
+                (false, false),
+                false,
+            );
+        }
+    }
+    image
+}
+
+/**
+ * The 32 palette entries, background palettes on the first row and sprite
+ * palettes on the second.
+ */
+pub fn palette(ppu: &PPU) -> Image {
+    let mut image = Image::new(PALETTE_WIDTH, PALETTE_HEIGHT);
+    for entry in 0..32 {
+        let colour = palette_colour(ppu, 0x3F00 + entry);
+        let x_offset = (entry as usize % 16) * PALETTE_SWATCH_SIZE;
+        let y_offset = (entry as usize / 16) * PALETTE_SWATCH_SIZE;
+        for y in 0..PALETTE_SWATCH_SIZE {
+            for x in 0..PALETTE_SWATCH_SIZE {
+                image.set_pixel(x_offset + x, y_offset + y, colour);
+            }
+        }
+    }
+    image
+}
+
+/**
+ * The 64 sprites in OAM, 8 per row, flipped and coloured as they would be
+ * drawn. Transparent pixels are left transparent.
+ */
+pub fn sprites(ppu: &PPU) -> Image {
+    let mut image = Image::new(SPRITES_WIDTH, SPRITES_HEIGHT);
+    let sprites = ppu.sprites();
+    for index in 0..64 {
+        let sprite = &sprites[index];
+        let x_offset = (index % 8) * 8;
+        let y_offset = (index / 8) * 16;
+        let flip = (sprite.flip_horizontal(), sprite.flip_vertical());
+        let palette = 4 + sprite.colour_palette();
+        if ppu.sprite_height() == 16 {
+            let pattern_table = (sprite.pattern_index() as u16 & 0x01) * 0x1000;
+            let top = pattern_table + (sprite.pattern_index() as u16 & 0xFE) * 16;
+            let (top, bottom) = if sprite.flip_vertical() {
+                (top + 16, top)
+            } else {
+                (top, top + 16)
+            };
+            draw_tile(
+                &mut image,
+                ppu,
+                top,
+                palette,
+                (x_offset, y_offset),
+                flip,
+                true,
+            );
+            draw_tile(
+                &mut image,
+                ppu,
+                bottom,
+                palette,
+                (x_offset, y_offset + 8),
+                flip,
+                true,
+            );
+        } else {
+            let address = ppu.sprite_pattern_table() + sprite.pattern_index() as u16 * 16;
+            draw_tile(
+                &mut image,
+                ppu,
+                address,
+                palette,
+                (x_offset, y_offset),
+                flip,
+                true,
+            );
+        }
+    }
+    image
+}
+
+/**
+ * One pixel per dot of the frame with the picture dimmed in the visible part
+ * and a marker for every event logged during the last complete frame.
+ */
+pub fn events(ppu: &PPU) -> Image {
+    let height = ppu.region().scanlines_per_frame() as usize;
+    let mut image = Image::new(EVENTS_WIDTH, height);
+    let frame = ppu.frame();
+    for y in 0..height {
+        for x in 0..EVENTS_WIDTH {
+            let colour = if y < SCREEN_HEIGHT && x >= 1 && x <= SCREEN_WIDTH {
+                let (red, green, blue) = frame.colour(x - 1, y);
+                (red / 3, green / 3, blue / 3)
+            } else {
+                (0, 0, 0)
+            };
+            image.set_pixel(x, y, colour);
+        }
+    }
+
+    if let Some(event_log) = ppu.events() {
+        for event in event_log.last_frame() {
+            let colour = event_colour(event.kind);
+            let (dot, scanline) = (event.dot as usize, event.scanline as usize);
+            for y in scanline.saturating_sub(1)..min(scanline + 2, height) {
+                for x in dot.saturating_sub(1)..min(dot + 2, EVENTS_WIDTH) {
+                    image.set_pixel(x, y, colour);
+                }
+            }
+        }
+    }
+    image
+}
+
+pub fn event_colour(kind: EventKind) -> Color {
+    match kind {
+        EventKind::RegisterWrite(register) => REGISTER_COLOURS[(register & 0x07) as usize],
+        EventKind::Nmi => (255, 255, 255),
+        EventKind::Irq => (255, 0, 255),
+    }
+}
+
+fn draw_tile(
+    image: &mut Image,
+    ppu: &PPU,
+    pattern_address: u16,
+    palette: u8,
+    (x_offset, y_offset): (usize, usize),
+    (flip_horizontal, flip_vertical): (bool, bool),
+    transparent: bool,
+) {
+    let memory = ppu.memory();
+    for row in 0..8 {
+        let pattern_row = if flip_vertical { 7 - row } else { row };
+        let low = memory.get(pattern_address + pattern_row, 0);
+        let high = memory.get(pattern_address + pattern_row + 8, 0);
+        for col in 0..8 {
+            let bit = if flip_horizontal { col } else { 7 - col };
+            let colour = (((high >> bit) & 0x01) << 1) | ((low >> bit) & 0x01);
+            if colour == 0 && transparent {
+                continue;
+            }
+            let palette_address = if colour == 0 {
+                0x3F00
+            } else {
+                0x3F00 + palette as u16 * 4 + colour as u16
+            };
+            image.set_pixel(
+                x_offset + col as usize,
+                y_offset + row as usize,
+                palette_colour(ppu, palette_address),
+            );
+        }
+    }
+}
+
+fn palette_colour(ppu: &PPU, palette_address: u16) -> Color {
+    let entry = ppu.memory().get(palette_address, 0) & 0x3F;
+    ppu.palette().colour(entry as u16)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        events, name_tables, palette, pattern_tables, sprites, REGISTER_COLOURS,
+        SCROLL_WINDOW_COLOUR,
+    };
+    use ppu::ppumemory::PPUMemory;
+    use ppu::screen::{ScreenMock, COLOUR_PALETTE};
+    use ppu::PPU;
+
+    fn opaque(index: usize) -> (u8, u8, u8, u8) {
+        let colour = COLOUR_PALETTE[index];
+        (colour.0, colour.1, colour.2, 255)
+    }
+
+    fn ppu_with_tile() -> PPU {
+        let mut ppu = PPU::new(PPUMemory::no_mirroring());
+        //Tile 1: colours 1, 2, 3 and 0 in the first row
+        ppu.load(0x0010, &[0b1010_0000]);
+        ppu.load(0x0018, &[0b0110_0000]);
+        ppu.load(0x3F00, &[0x0F, 0x01, 0x02, 0x03, 0x0F, 0x11, 0x12, 0x13]);
+        ppu.load(0x3F10, &[0x0F, 0x21, 0x22, 0x23, 0x0F, 0x31, 0x32, 0x33]);
+        ppu
+    }
+
+    #[test]
+    fn pattern_tables_should_use_the_chosen_palette() {
+        let ppu = ppu_with_tile();
+        let image = pattern_tables(&ppu, 1);
+        assert_eq!(opaque(0x11), image.pixel(8, 0));
+        assert_eq!(opaque(0x12), image.pixel(9, 0));
+        assert_eq!(opaque(0x13), image.pixel(10, 0));
+        assert_eq!(opaque(0x0F), image.pixel(11, 0));
+
+        let image = pattern_tables(&ppu, 5);
+        assert_eq!(opaque(0x31), image.pixel(8, 0));
+    }
+
+    #[test]
+    fn name_tables_should_outline_the_scroll_window() {
+        let mut ppu = ppu_with_tile();
+        ppu.load(0x2C21, &[0x01]); //Name table 3, row 1, col 1
+        ppu.load(0x2FC0, &[0x01]); //Palette 1 for the top left corner
+        ppu.set_ppu_ctrl(0x03);
+        ppu.set_scroll(4);
+        ppu.set_scroll(2);
+
+        let image = name_tables(&ppu);
+        assert_eq!(opaque(0x0F), image.pixel(256, 240 + 8));
+        assert_eq!(opaque(0x11), image.pixel(256 + 8, 240 + 8));
+        assert_eq!(opaque(0x12), image.pixel(256 + 9, 240 + 8));
+
+        let outline = (
+            SCROLL_WINDOW_COLOUR.0,
+            SCROLL_WINDOW_COLOUR.1,
+            SCROLL_WINDOW_COLOUR.2,
+            255,
+        );
+        assert_eq!(outline, image.pixel(256 + 4, 240 + 2));
+        //Wraps around to name table 0
+        assert_eq!(outline, image.pixel(3, 240 + 2));
+        assert_eq!(outline, image.pixel(256 + 4, 1));
+        assert_eq!(opaque(0x0F), image.pixel(4, 2));
+    }
+
+    #[test]
+    fn palette_should_show_background_and_sprite_entries() {
+        let ppu = ppu_with_tile();
+        let image = palette(&ppu);
+        assert_eq!(opaque(0x01), image.pixel(16, 0));
+        assert_eq!(opaque(0x13), image.pixel(7 * 16 + 15, 15));
+        assert_eq!(opaque(0x21), image.pixel(16, 16));
+    }
+
+    #[test]
+    fn sprites_should_be_flipped_and_transparent() {
+        let mut ppu = ppu_with_tile();
+        ppu.sprites_mut().slice()[0..8].copy_from_slice(&[0, 1, 0x41, 0, 0, 1, 0x00, 0]);
+
+        let image = sprites(&ppu);
+        assert_eq!(opaque(0x33), image.pixel(5, 0));
+        assert_eq!(opaque(0x31), image.pixel(7, 0));
+        assert_eq!((0, 0, 0, 0), image.pixel(4, 0));
+        assert_eq!(opaque(0x21), image.pixel(8, 0));
+        assert_eq!((0, 0, 0, 0), image.pixel(8, 1));
+    }
+
+    #[test]
+    fn tall_sprites_should_use_the_pattern_table_of_the_index() {
+        let mut ppu = ppu_with_tile();
+        ppu.load(0x1030, &[0xFF]); //Bottom half of tiles 2 and 3 in the second table
+        ppu.set_ppu_ctrl(0x20);
+        ppu.sprites_mut().slice()[0..8].copy_from_slice(&[0, 3, 0x00, 0, 0, 3, 0x80, 0]);
+
+        let image = sprites(&ppu);
+        assert_eq!((0, 0, 0, 0), image.pixel(0, 0));
+        assert_eq!(opaque(0x21), image.pixel(0, 8));
+        assert_eq!((0, 0, 0, 0), image.pixel(8, 0));
+        assert_eq!(opaque(0x21), image.pixel(8, 7));
+        assert_eq!((0, 0, 0, 0), image.pixel(8, 8));
+    }
+
+    #[test]
+    fn events_should_be_marked_at_their_dot() {
+        let screen = &mut ScreenMock::new();
+        let mut ppu = ppu_with_tile();
+        ppu.set_event_logging(true);
+        ppu.sync(100, screen);
+        ppu.log_register_write(0x2006, 0x3F, 0);
+        while ppu.frame_count() == 0 {
+            ppu.sync(1, screen);
+        }
+
+        let image = events(&ppu);
+        assert_eq!(341, image.width);
+        assert_eq!(262, image.height);
+        let (red, green, blue) = REGISTER_COLOURS[6];
+        assert_eq!((red, green, blue, 255), image.pixel(303, 0));
+        assert_eq!((red, green, blue, 255), image.pixel(304, 1));
+        assert_eq!((0, 0, 0, 255), image.pixel(300, 100));
+    }
+}
@@ -59,6 +59,35 @@ impl VRAMRegisters {
         self.current = (self.current & 0b111_1011_1110_0000) | horizontal_bits;
     }
 
+    pub fn copy_vertical_bits(&mut self) {
+        let vertical_bits = self.temporary & 0b111_1011_1110_0000;
+        self.current = (self.current & 0b000_0100_0001_1111) | vertical_bits;
+    }
+
+    /**
+     * Address of the name table entry for the tile at the current position.
+     */
+    pub fn tile_address(&self) -> u16 {
+        0x2000 | (self.current & 0x0FFF)
+    }
+
+    /**
+     * Address of the attribute byte covering the tile at the current position.
+     */
+    pub fn attribute_address(&self) -> u16 {
+        0x23C0
+            | (self.current & 0x0C00)
+            | ((self.current >> 4) & 0x38)
+            | ((self.current >> 2) & 0x07)
+    }
+
+    /**
+     * Right shift that selects the two attribute bits of the current tile.
+     */
+    pub fn attribute_shift(&self) -> u8 {
+        (((self.current >> 4) & 0x04) | (self.current & 0x02)) as u8
+    }
+
     pub fn write_name_table(&mut self, value: u8) {
         let name_table = (value & 0x03) as u16;
         self.temporary = self.temporary & 0xF3FF | (name_table << 10);
@@ -79,6 +108,12 @@ impl VRAMRegisters {
         self.write_toggle = false;
     }
 
+    pub fn reset(&mut self) {
+        self.temporary = 0;
+        self.fine_x = 0;
+        self.write_toggle = false;
+    }
+
     pub fn copy_temporary_bits(&mut self) {
         self.current = self.temporary;
     }
@@ -87,6 +122,10 @@ impl VRAMRegisters {
         (self.current >> 12) as u8
     }
 
+    pub fn fine_x(&self) -> u8 {
+        self.fine_x
+    }
+
     pub fn temporary_x_scroll(&self) -> u8 {
         (self.temporary & 0x1F) as u8
     }
@@ -95,6 +134,14 @@ impl VRAMRegisters {
         ((self.temporary & 0x7000) >> 12) as u8 | ((self.temporary & 0x3E0) >> 2) as u8
     }
 
+    pub fn temporary_absolute_x_scroll(&self) -> u16 {
+        ((self.temporary & 0x400) >> 2) + ((self.temporary_x_scroll() << 3) | self.fine_x) as u16
+    }
+
+    pub fn temporary_absolute_y_scroll(&self) -> u16 {
+        ((self.temporary & 0x800) >> 11) * 240 + self.temporary_y_scroll() as u16
+    }
+
     pub fn current_x_scroll(&self) -> u8 {
         (((self.current & 0x1F) as u8) << 3) | self.fine_x
     }
@@ -152,6 +199,21 @@ mod tests {
         assert_eq!(0b000_0000_0100_0010, registers.temporary);
     }
 
+    #[test]
+    fn temporary_absolute_scroll_should_include_name_table_and_fine_x() {
+        let mut registers = VRAMRegisters::new();
+        registers.write_name_table(0x03);
+        registers.write_scroll(0x2D);
+        registers.write_scroll(0x13);
+        assert_eq!(
+            (256 + 0x2D, 240 + 0x13),
+            (
+                registers.temporary_absolute_x_scroll(),
+                registers.temporary_absolute_y_scroll()
+            )
+        );
+    }
+
     #[test]
     fn write_fine_y_scroll() {
         let mut registers = VRAMRegisters::new();
@@ -310,6 +372,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn copy_vertical_bits() {
+        for _ in 0..100 {
+            let temp_vram = rand::random::<u16>();
+            let mut vram = VRAMRegisters::with_temp(temp_vram);
+
+            vram.copy_vertical_bits();
+            assert_eq!(
+                temp_vram & 0b111_1011_1110_0000,
+                vram.current,
+                "Copy temp value: {:04x} -> {:04x}",
+                temp_vram,
+                vram.current
+            )
+        }
+    }
+
+    #[test]
+    fn tile_and_attribute_addresses() {
+        //Name table 3, coarse y 29, coarse x 31, fine y 5
+        let vram = VRAMRegisters::with_current(0b101_1111_1011_1111);
+        assert_eq!(0x2FBF, vram.tile_address());
+        assert_eq!(0x2FFF, vram.attribute_address());
+        assert_eq!(2, vram.attribute_shift());
+
+        //Name table 0, coarse y 2, coarse x 5
+        let vram = VRAMRegisters::with_current(0b000_0000_0100_0101);
+        assert_eq!(0x2045, vram.tile_address());
+        assert_eq!(0x23C1, vram.attribute_address());
+        assert_eq!(4, vram.attribute_shift());
+    }
+
     #[test]
     fn write_name_table() {
         for _ in 0..100 {
@@ -0,0 +1,247 @@
+use std::str::FromStr;
+
+/**
+ * The TV system the console was made for. Besides the frame rate this changes
+ * the clock dividers, the PPU frame layout and the APU tables.
+ */
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Region {
+    #[default]
+    NTSC,
+    PAL,
+    /**
+     * Famiclone with a PAL frame rate but NTSC like CPU and APU timing.
+     */
+    Dendy,
+}
+
+const NTSC_APU_FRAME_STEPS: [u32; 5] = [7457, 14913, 22371, 29829, 37281];
+const PAL_APU_FRAME_STEPS: [u32; 5] = [8313, 16627, 24939, 33253, 41565];
+const NTSC_NOISE_PERIODS: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+const PAL_NOISE_PERIODS: [u16; 16] = [
+    4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778,
+];
+const NTSC_DMC_RATES: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+const PAL_DMC_RATES: [u16; 16] = [
+    398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+];
+
+impl Region {
+    /**
+     * Reads the timing from byte 12 of an NES 2.0 header, or the TV system
+     * bit in byte 9 of an iNES header. Multi-region games run as NTSC.
+     */
+    pub fn from_header(header: &[u8]) -> Region {
+        let nes_2_0 = header[7] & 0x0C == 0x08;
+        if nes_2_0 {
+            match header[12] & 0x03 {
+                1 => Region::PAL,
+                3 => Region::Dendy,
+                _ => Region::NTSC,
+            }
+        } else if header[9] & 0x01 != 0 {
+            Region::PAL
+        } else {
+            Region::NTSC
+        }
+    }
+
+    pub fn nanos_per_cpu_cycle(&self) -> u32 {
+        match *self {
+            Region::NTSC => 559,  //21.477272 MHz / 12
+            Region::PAL => 601,   //26.601712 MHz / 16
+            Region::Dendy => 564, //26.601712 MHz / 15
+        }
+    }
+
+    /**
+     * CPU cycles per second.
+     */
+    pub fn cpu_clock_rate(&self) -> u32 {
+        match *self {
+            Region::NTSC => 1_789_773,
+            Region::PAL => 1_662_607,
+            Region::Dendy => 1_773_448,
+        }
+    }
+
+    /**
+     * PPU dots per 5 CPU cycles, PAL runs 3.2 dots per CPU cycle.
+     */
+    pub fn ppu_cycles_per_5_cpu_cycles(&self) -> u32 {
+        match *self {
+            Region::PAL => 16,
+            Region::NTSC | Region::Dendy => 15,
+        }
+    }
+
+    /**
+     * CPU cycles per frame rounded up, the frames alternate between slightly
+     * shorter and longer ones.
+     */
+    pub fn cpu_cycles_per_frame(&self) -> u32 {
+        let ppu_cycles = self.scanlines_per_frame() * 341 * 5;
+        ppu_cycles.div_ceil(self.ppu_cycles_per_5_cpu_cycles())
+    }
+
+    pub fn scanlines_per_frame(&self) -> u32 {
+        match *self {
+            Region::NTSC => 262,
+            Region::PAL | Region::Dendy => 312,
+        }
+    }
+
+    /**
+     * Idle scanlines between the picture and the start of vblank.
+     */
+    pub fn post_render_scanlines(&self) -> u32 {
+        match *self {
+            Region::NTSC | Region::PAL => 1,
+            Region::Dendy => 51,
+        }
+    }
+
+    pub fn vblank_scanlines(&self) -> u32 {
+        match *self {
+            Region::NTSC | Region::Dendy => 20,
+            Region::PAL => 70,
+        }
+    }
+
+    /**
+     * Only the NTSC PPU drops a dot on odd frames when rendering is enabled.
+     */
+    pub fn skips_dot_on_odd_frames(&self) -> bool {
+        *self == Region::NTSC
+    }
+
+    /**
+     * The PAL PPU (and the Dendy clones of it) swaps the red and green emphasis
+     * bits. This is the only difference in colour between the regions, there
+     * is no separate PAL palette.
+     */
+    pub fn swaps_red_and_green_emphasis(&self) -> bool {
+        *self != Region::NTSC
+    }
+
+    /**
+     * CPU cycles after which the APU frame counter takes each of its five
+     * steps, the 4-step sequence ends one cycle after the fourth.
+     */
+    pub fn apu_frame_steps(&self) -> &'static [u32; 5] {
+        match *self {
+            Region::NTSC | Region::Dendy => &NTSC_APU_FRAME_STEPS,
+            Region::PAL => &PAL_APU_FRAME_STEPS,
+        }
+    }
+
+    /**
+     * Timer periods of the noise channel in CPU cycles.
+     */
+    pub fn noise_periods(&self) -> &'static [u16; 16] {
+        match *self {
+            Region::NTSC | Region::Dendy => &NTSC_NOISE_PERIODS,
+            Region::PAL => &PAL_NOISE_PERIODS,
+        }
+    }
+
+    /**
+     * Output rates of the delta modulation channel in CPU cycles.
+     */
+    pub fn dmc_rates(&self) -> &'static [u16; 16] {
+        match *self {
+            Region::NTSC | Region::Dendy => &NTSC_DMC_RATES,
+            Region::PAL => &PAL_DMC_RATES,
+        }
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Region, String> {
+        match s {
+            "ntsc" => Ok(Region::NTSC),
+            "pal" => Ok(Region::PAL),
+            "dendy" => Ok(Region::Dendy),
+            _ => Err(format!("Unknown region '{}'", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Region;
+
+    fn header(byte_7: u8, byte_9: u8, byte_12: u8) -> [u8; 16] {
+        let mut header = [0; 16];
+        header[0..4].copy_from_slice(b"NES\x1A");
+        header[7] = byte_7;
+        header[9] = byte_9;
+        header[12] = byte_12;
+        header
+    }
+
+    #[test]
+    fn region_should_be_read_from_the_header() {
+        assert_eq!(Region::NTSC, Region::from_header(&header(0x00, 0x00, 0x00)));
+        assert_eq!(Region::PAL, Region::from_header(&header(0x00, 0x01, 0x00)));
+
+        assert_eq!(Region::NTSC, Region::from_header(&header(0x08, 0x01, 0x00)));
+        assert_eq!(Region::PAL, Region::from_header(&header(0x08, 0x00, 0x01)));
+        assert_eq!(Region::NTSC, Region::from_header(&header(0x08, 0x00, 0x02)));
+        assert_eq!(
+            Region::Dendy,
+            Region::from_header(&header(0x08, 0x00, 0x03))
+        );
+    }
+
+    #[test]
+    fn frame_should_consist_of_picture_post_render_vblank_and_pre_render() {
+        for &region in [Region::NTSC, Region::PAL, Region::Dendy].iter() {
+            assert_eq!(
+                region.scanlines_per_frame(),
+                240 + region.post_render_scanlines() + region.vblank_scanlines() + 1
+            );
+        }
+    }
+
+    #[test]
+    fn cpu_cycles_per_frame_should_be_rounded_up() {
+        assert_eq!(29_781, Region::NTSC.cpu_cycles_per_frame());
+        assert_eq!(33_248, Region::PAL.cpu_cycles_per_frame());
+        assert_eq!(35_464, Region::Dendy.cpu_cycles_per_frame());
+    }
+
+    #[test]
+    fn cpu_clock_rate_should_match_the_cycle_length() {
+        for &region in [Region::NTSC, Region::PAL, Region::Dendy].iter() {
+            let nanos = 1e9 / region.cpu_clock_rate() as f64;
+            assert!((nanos - region.nanos_per_cpu_cycle() as f64).abs() < 1.0);
+        }
+    }
+
+    #[test]
+    fn frames_should_take_the_same_time_as_on_the_hardware() {
+        for &(region, frame_rate) in [
+            (Region::NTSC, 60.10),
+            (Region::PAL, 50.01),
+            (Region::Dendy, 50.01),
+        ]
+        .iter()
+        {
+            let dots = region.scanlines_per_frame() * 341;
+            let nanos = dots as f64 * 5.0 / region.ppu_cycles_per_5_cpu_cycles() as f64
+                * region.nanos_per_cpu_cycle() as f64;
+            assert!(
+                (1e9 / nanos - frame_rate).abs() < 0.05,
+                "{:?} runs at {} fps",
+                region,
+                1e9 / nanos
+            );
+        }
+    }
+}
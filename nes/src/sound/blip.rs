@@ -0,0 +1,210 @@
+use std::f64::consts::PI;
+
+const KERNEL_WIDTH: usize = 16;
+const PHASES: usize = 64;
+/**
+ * Cut off frequency of the kernel relative to the sample rate, a bit below
+ * the Nyquist frequency.
+ */
+const CUTOFF: f64 = 0.45;
+
+/**
+ * Band-limited synthesis buffer. Every change of the input, given in CPU
+ * cycles, is added as a band-limited step so the output at the sample rate
+ * does not alias, and the samples keep in step with the CPU clock.
+ */
+pub struct BlipBuffer {
+    samples_per_cycle: f64,
+    clock_rate: u32,
+    sample_rate: u32,
+    rate_adjustment: f64,
+    kernel: Vec<[f32; KERNEL_WIDTH]>,
+    /**
+     * Differences between consecutive samples, starting at the first sample
+     * not read yet.
+     */
+    deltas: Vec<f32>,
+    /**
+     * Position of the start of the frame in `deltas`, in samples.
+     */
+    frame_start: f64,
+    integrator: f32,
+}
+
+impl BlipBuffer {
+    pub fn new(clock_rate: u32, sample_rate: u32) -> BlipBuffer {
+        BlipBuffer {
+            samples_per_cycle: sample_rate as f64 / clock_rate as f64,
+            clock_rate: clock_rate,
+            sample_rate: sample_rate,
+            rate_adjustment: 1.0,
+            kernel: (0..PHASES + 1).map(kernel_phase).collect(),
+            deltas: vec![0.0; KERNEL_WIDTH],
+            frame_start: 0.0,
+            integrator: 0.0,
+        }
+    }
+
+    pub fn set_clock_rate(&mut self, clock_rate: u32) {
+        self.clock_rate = clock_rate;
+        self.update_samples_per_cycle();
+    }
+
+    /**
+     * Makes slightly more (above 1) or fewer samples than the sample rate.
+     */
+    pub fn set_rate_adjustment(&mut self, rate_adjustment: f64) {
+        self.rate_adjustment = rate_adjustment;
+        self.update_samples_per_cycle();
+    }
+
+    pub fn rate_adjustment(&self) -> f64 {
+        self.rate_adjustment
+    }
+
+    fn update_samples_per_cycle(&mut self) {
+        self.samples_per_cycle =
+            self.sample_rate as f64 * self.rate_adjustment / self.clock_rate as f64;
+    }
+
+    /**
+     * Adds a change of the input `cycles` CPU cycles into the frame.
+     */
+    pub fn add_delta(&mut self, cycles: u32, delta: f32) {
+        let position = self.frame_start + cycles as f64 * self.samples_per_cycle;
+        let index = position as usize;
+        let phase = ((position - index as f64) * PHASES as f64).round() as usize;
+        if self.deltas.len() < index + KERNEL_WIDTH {
+            self.deltas.resize(index + KERNEL_WIDTH, 0.0);
+        }
+        for (sample, &step) in self.deltas[index..]
+            .iter_mut()
+            .zip(self.kernel[phase].iter())
+        {
+            *sample += delta * step;
+        }
+    }
+
+    /**
+     * Ends the frame after `cycles` CPU cycles, the samples before its end
+     * can then be read.
+     */
+    pub fn end_frame(&mut self, cycles: u32) {
+        self.frame_start += cycles as f64 * self.samples_per_cycle;
+    }
+
+    pub fn samples_available(&self) -> usize {
+        self.frame_start as usize
+    }
+
+    pub fn read_samples(&mut self, samples: &mut Vec<f32>) {
+        let available = self.samples_available();
+        if self.deltas.len() < available + KERNEL_WIDTH {
+            self.deltas.resize(available + KERNEL_WIDTH, 0.0);
+        }
+        for delta in self.deltas.drain(0..available) {
+            self.integrator += delta;
+            samples.push(self.integrator);
+        }
+        self.frame_start -= available as f64;
+    }
+}
+
+/**
+ * Windowed sinc impulse for a step `phase / PHASES` of a sample after the
+ * start of the kernel, adding up to 1.
+ */
+fn kernel_phase(phase: usize) -> [f32; KERNEL_WIDTH] {
+    let half_width = (KERNEL_WIDTH / 2) as f64;
+    let offset = phase as f64 / PHASES as f64;
+    let mut impulse = [0.0; KERNEL_WIDTH];
+    for (i, value) in impulse.iter_mut().enumerate() {
+        let t = i as f64 - half_width - offset;
+        let sinc = if t == 0.0 {
+            1.0
+        } else {
+            (2.0 * PI * CUTOFF * t).sin() / (2.0 * PI * CUTOFF * t)
+        };
+        let window = if t.abs() < half_width {
+            0.42 + 0.5 * (PI * t / half_width).cos() + 0.08 * (2.0 * PI * t / half_width).cos()
+        } else {
+            0.0
+        };
+        *value = sinc * window;
+    }
+    let sum: f64 = impulse.iter().sum();
+    let mut kernel = [0.0; KERNEL_WIDTH];
+    for (step, value) in kernel.iter_mut().zip(impulse.iter()) {
+        *step = (value / sum) as f32;
+    }
+    kernel
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BlipBuffer, KERNEL_WIDTH};
+
+    #[test]
+    fn samples_should_follow_the_ratio_of_the_rates_exactly() {
+        let mut blip = BlipBuffer::new(1_789_773, 48_000);
+        let mut samples = vec![];
+        let mut cycles = 0u64;
+        while cycles < 1_789_773 * 10 {
+            blip.end_frame(3000);
+            blip.read_samples(&mut samples);
+            cycles += 3000;
+        }
+        let expected = (cycles * 48_000 / 1_789_773) as usize;
+        assert!(
+            samples.len() == expected || samples.len() + 1 == expected,
+            "{} samples",
+            samples.len()
+        );
+    }
+
+    #[test]
+    fn rate_adjustment_should_change_the_number_of_samples() {
+        let mut blip = BlipBuffer::new(1_000_000, 48_000);
+        blip.set_rate_adjustment(1.005);
+        let mut samples = vec![];
+        for _ in 0..1000 {
+            blip.end_frame(1000);
+            blip.read_samples(&mut samples);
+        }
+        assert!(
+            (samples.len() as i64 - 48_240).abs() <= 1,
+            "{} samples",
+            samples.len()
+        );
+    }
+
+    #[test]
+    fn step_should_settle_at_the_new_level() {
+        let mut blip = BlipBuffer::new(1_789_773, 48_000);
+        blip.add_delta(0, 1.0);
+        blip.end_frame(1000);
+        let mut samples = vec![];
+        blip.read_samples(&mut samples);
+
+        assert!(samples[0].abs() < 0.001);
+        for &sample in samples[KERNEL_WIDTH..].iter() {
+            assert!((sample - 1.0).abs() < 0.001, "{:?}", samples);
+        }
+        for &sample in samples.iter() {
+            assert!(sample > -0.15 && sample < 1.15, "{:?}", samples);
+        }
+    }
+
+    #[test]
+    fn steps_should_carry_over_to_the_next_frame() {
+        let mut blip = BlipBuffer::new(1_789_773, 48_000);
+        let mut samples = vec![];
+        blip.add_delta(370, 0.5);
+        blip.end_frame(370);
+        blip.read_samples(&mut samples);
+        blip.end_frame(1000);
+        blip.read_samples(&mut samples);
+
+        assert!((samples[samples.len() - 1] - 0.5).abs() < 0.001);
+    }
+}
@@ -0,0 +1,270 @@
+use region::Region;
+
+/**
+ * CPU cycles the CPU is stalled while the DMC fetches a sample byte.
+ */
+pub const DMA_CYCLES: u8 = 4;
+
+/**
+ * The delta modulation channel, playing 1 bit delta encoded samples read from
+ * $8000-$FFFF. The sample bytes are fetched by whoever owns the CPU bus, see
+ * `sample_address` and `load_sample`.
+ */
+pub struct DeltaModulationChannel {
+    rates: &'static [u16; 16],
+    irq_enabled: bool,
+    loop_flag: bool,
+    timer_set: u32,
+    timer: u32,
+    output_level: u8,
+
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+    irq: bool,
+}
+
+impl DeltaModulationChannel {
+    pub fn new() -> DeltaModulationChannel {
+        DeltaModulationChannel {
+            rates: Region::NTSC.dmc_rates(),
+            irq_enabled: false,
+            loop_flag: false,
+            timer_set: Region::NTSC.dmc_rates()[0] as u32,
+            timer: 0,
+            output_level: 0,
+
+            sample_address: 0xC000,
+            sample_length: 1,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+            irq: false,
+        }
+    }
+
+    /**
+     * Clearing the IRQ enable flag also acknowledges a pending IRQ.
+     */
+    pub fn flags_and_rate(&mut self, irq_enabled: bool, loop_flag: bool, rate_index: u8) {
+        self.irq_enabled = irq_enabled;
+        if !irq_enabled {
+            self.irq = false;
+        }
+        self.loop_flag = loop_flag;
+        self.timer_set = self.rates[(rate_index & 0x0F) as usize] as u32;
+    }
+
+    pub fn direct_load(&mut self, value: u8) {
+        self.output_level = value & 0x7F;
+    }
+
+    pub fn sample_address_register(&mut self, value: u8) {
+        self.sample_address = 0xC000 + ((value as u16) << 6);
+    }
+
+    pub fn sample_length_register(&mut self, value: u8) {
+        self.sample_length = ((value as u16) << 4) + 1;
+    }
+
+    /**
+     * Enabling restarts the sample if it has finished, disabling stops it
+     * after the bits already read. Either way a pending IRQ is acknowledged.
+     */
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.irq = false;
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    pub fn irq(&self) -> bool {
+        self.irq
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        let rate_index = self
+            .rates
+            .iter()
+            .position(|&rate| rate as u32 == self.timer_set)
+            .unwrap_or(0);
+        self.rates = region.dmc_rates();
+        self.timer_set = self.rates[rate_index] as u32;
+    }
+
+    pub fn silence(&mut self) {
+        self.set_enabled(false);
+        self.irq_enabled = false;
+        self.sample_buffer = None;
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    /**
+     * The address of the next sample byte, if the sample buffer needs one.
+     */
+    pub fn sample_address(&self) -> Option<u16> {
+        if self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.current_address)
+        } else {
+            None
+        }
+    }
+
+    /**
+     * Fills the sample buffer with the byte read from `sample_address`.
+     */
+    pub fn load_sample(&mut self, value: u8) {
+        self.sample_buffer = Some(value);
+        self.current_address = if self.current_address == 0xFFFF {
+            0x8000
+        } else {
+            self.current_address + 1
+        };
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 {
+            if self.loop_flag {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq = true;
+            }
+        }
+    }
+
+    pub fn update(&mut self, cpu_cycles: u8) {
+        self.timer += cpu_cycles as u32;
+        while self.timer >= self.timer_set {
+            self.timer -= self.timer_set;
+            self.clock_output();
+        }
+    }
+
+    fn clock_output(&mut self) {
+        if !self.silence {
+            if self.shift_register & 0x01 != 0 {
+                if self.output_level <= 125 {
+                    self.output_level += 2;
+                }
+            } else if self.output_level >= 2 {
+                self.output_level -= 2;
+            }
+        }
+        self.shift_register >>= 1;
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(sample) => {
+                    self.silence = false;
+                    self.shift_register = sample;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    pub fn dmc_value(&self) -> i16 {
+        self.output_level as i16
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DeltaModulationChannel;
+
+    fn playing(length_register: u8) -> DeltaModulationChannel {
+        let mut dmc = DeltaModulationChannel::new();
+        dmc.sample_address_register(0xFF);
+        dmc.sample_length_register(length_register);
+        dmc.set_enabled(true);
+        dmc
+    }
+
+    #[test]
+    fn sample_should_be_read_from_the_sample_address_and_wrap_to_8000() {
+        let mut dmc = playing(0x01);
+        assert_eq!(17, dmc.bytes_remaining);
+        assert_eq!(Some(0xFFC0), dmc.sample_address());
+        dmc.load_sample(0);
+        assert_eq!(None, dmc.sample_address());
+
+        dmc.current_address = 0xFFFF;
+        dmc.sample_buffer = None;
+        dmc.load_sample(0);
+        assert_eq!(0x8000, dmc.current_address);
+    }
+
+    #[test]
+    fn irq_should_be_raised_at_the_end_of_the_sample_if_enabled() {
+        let mut dmc = playing(0x00);
+        dmc.flags_and_rate(true, false, 0x0F);
+        dmc.load_sample(0);
+        assert!(dmc.irq());
+        assert!(!dmc.is_active());
+
+        dmc.flags_and_rate(false, false, 0x0F);
+        assert!(!dmc.irq());
+    }
+
+    #[test]
+    fn looping_sample_should_restart_without_irq() {
+        let mut dmc = playing(0x00);
+        dmc.flags_and_rate(true, true, 0x0F);
+        dmc.load_sample(0);
+        assert!(!dmc.irq());
+        assert!(dmc.is_active());
+        assert_eq!(0xFFC0, dmc.current_address);
+    }
+
+    #[test]
+    fn output_level_should_follow_the_sample_bits() {
+        let mut dmc = playing(0x00);
+        dmc.flags_and_rate(false, false, 0x0F);
+        dmc.direct_load(0x40);
+        dmc.load_sample(0b0000_0111);
+
+        //The first 8 bits are played from the empty shift register while silenced
+        for _ in 0..8 {
+            dmc.update(54);
+        }
+        assert_eq!(0x40, dmc.dmc_value());
+
+        let mut levels = vec![];
+        for _ in 0..8 {
+            dmc.update(54);
+            levels.push(dmc.dmc_value());
+        }
+        assert_eq!(vec![0x42, 0x44, 0x46, 0x44, 0x42, 0x40, 0x3E, 0x3C], levels);
+    }
+
+    #[test]
+    fn output_level_should_saturate() {
+        let mut dmc = playing(0x00);
+        dmc.flags_and_rate(false, false, 0x0F);
+        dmc.direct_load(0x7F);
+        dmc.load_sample(0xFF);
+        for _ in 0..16 {
+            dmc.update(54);
+        }
+        assert_eq!(0x7F, dmc.dmc_value());
+    }
+}
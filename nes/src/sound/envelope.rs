@@ -1,10 +1,7 @@
-const APU_CYCLES_CLOCK_RATE: u32 = 14913;
-
 pub struct Envelope {
     divider: u8,
     volume: u8,
     decay_level: u8,
-    cpu_cycles: u32,
     constant_volume: bool,
 }
 
@@ -14,7 +11,6 @@ impl Envelope {
             volume: volume,
             divider: volume,
             decay_level: 15,
-            cpu_cycles: 0,
             constant_volume: false,
         }
     }
@@ -24,22 +20,20 @@ impl Envelope {
             volume: volume,
             divider: 0,
             decay_level: volume,
-            cpu_cycles: 0,
             constant_volume: true,
         }
     }
 
-    pub fn clock(&mut self, cpu_cycles: u8) {
+    /**
+     * Clocked by the frame counter every quarter frame.
+     */
+    pub fn clock(&mut self) {
         if self.decay_level > 0 && !self.constant_volume {
-            self.cpu_cycles += cpu_cycles as u32;
-            if self.cpu_cycles >= APU_CYCLES_CLOCK_RATE {
-                if self.divider == 0 {
-                    self.decay_level -= 1;
-                    self.divider = self.volume;
-                } else {
-                    self.divider -= 1;
-                }
-                self.cpu_cycles -= APU_CYCLES_CLOCK_RATE;
+            if self.divider == 0 {
+                self.decay_level -= 1;
+                self.divider = self.volume;
+            } else {
+                self.divider -= 1;
             }
         }
     }
@@ -52,44 +46,33 @@ impl Envelope {
 #[cfg(test)]
 mod test {
     use super::Envelope;
-    use sound::counter::ClockTester;
 
     #[test]
-    fn decaying_every_14913_clock_cycles() {
-        let envelope = Envelope::decaying(10);
+    fn decaying_every_volume_plus_one_clocks() {
+        let mut envelope = Envelope::decaying(10);
         assert_eq!(envelope.value(), 15);
-        let mut value = 15;
-        let mut clock = ClockTester::new(envelope, (10 + 1) * super::APU_CYCLES_CLOCK_RATE);
-        for i in 0..15 {
-            println!("Iteration {}", i);
-            clock.count_down(
-                |counter, tick| counter.clock(tick),
-                &|counter, cycles| assert_eq!(counter.value(), value, "Failed on clock {}", cycles),
-                &|counter, _| assert_eq!(counter.value(), value - 1),
-            );
-            value -= 1;
+        for value in (0..15).rev() {
+            for clock in 0..10 {
+                envelope.clock();
+                assert_eq!(envelope.value(), value + 1, "Failed on clock {}", clock);
+            }
+            envelope.clock();
+            assert_eq!(envelope.value(), value);
         }
 
-        for _ in 0..10 {
-            clock.count_down(
-                |counter, tick| counter.clock(tick),
-                &|counter, cycles| assert_eq!(counter.value(), 0, "Failed on clock {}", cycles),
-                &|counter, _| assert_eq!(counter.value(), 0),
-            );
+        for _ in 0..100 {
+            envelope.clock();
+            assert_eq!(envelope.value(), 0);
         }
     }
 
     #[test]
     fn constant_volume() {
-        let envelope = Envelope::constant(10);
+        let mut envelope = Envelope::constant(10);
         assert_eq!(envelope.value(), 10);
-        let mut clock = ClockTester::new(envelope, (10 + 1) * super::APU_CYCLES_CLOCK_RATE);
-        for _ in 0..15 {
-            clock.count_down(
-                |counter, tick| counter.clock(tick),
-                &|counter, cycles| assert_eq!(counter.value(), 10, "Failed on clock {}", cycles),
-                &|counter, _| assert_eq!(counter.value(), 10),
-            );
+        for _ in 0..200 {
+            envelope.clock();
+            assert_eq!(envelope.value(), 10);
         }
     }
 }
@@ -0,0 +1,85 @@
+use std::f32::consts::PI;
+
+/**
+ * First order filter, the NES has two high-pass and one low-pass filter
+ * between the DAC and the output.
+ */
+pub struct Filter {
+    high_pass: bool,
+    alpha: f32,
+    previous_input: f32,
+    previous_output: f32,
+}
+
+impl Filter {
+    pub fn high_pass(cutoff: f32, sample_rate: u32) -> Filter {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate as f32;
+        Filter {
+            high_pass: true,
+            alpha: rc / (rc + dt),
+            previous_input: 0.0,
+            previous_output: 0.0,
+        }
+    }
+
+    pub fn low_pass(cutoff: f32, sample_rate: u32) -> Filter {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate as f32;
+        Filter {
+            high_pass: false,
+            alpha: dt / (rc + dt),
+            previous_input: 0.0,
+            previous_output: 0.0,
+        }
+    }
+
+    pub fn apply(&mut self, input: f32) -> f32 {
+        let output = if self.high_pass {
+            self.alpha * (self.previous_output + input - self.previous_input)
+        } else {
+            self.previous_output + self.alpha * (input - self.previous_output)
+        };
+        self.previous_input = input;
+        self.previous_output = output;
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Filter;
+
+    #[test]
+    fn high_pass_should_remove_a_constant_level() {
+        let mut filter = Filter::high_pass(90.0, 48_000);
+        assert!((filter.apply(1.0) - 1.0).abs() < 0.02);
+        let mut output = 1.0;
+        for _ in 0..48_000 {
+            output = filter.apply(1.0);
+        }
+        assert!(output.abs() < 0.001);
+    }
+
+    #[test]
+    fn low_pass_should_keep_a_constant_level() {
+        let mut filter = Filter::low_pass(14_000.0, 48_000);
+        assert!(filter.apply(1.0) < 0.9);
+        let mut output = 0.0;
+        for _ in 0..100 {
+            output = filter.apply(1.0);
+        }
+        assert!((output - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn low_pass_should_dampen_high_frequencies() {
+        let mut filter = Filter::low_pass(14_000.0, 48_000);
+        let mut peak: f32 = 0.0;
+        for i in 0..1000 {
+            let input = if i % 2 == 0 { 1.0 } else { -1.0 };
+            peak = peak.max(filter.apply(input).abs());
+        }
+        assert!(peak < 0.8);
+    }
+}
@@ -0,0 +1,252 @@
+use region::Region;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrameClock {
+    /**
+     * Clocks the envelopes and the linear counter of the triangle.
+     */
+    QuarterFrame,
+    /**
+     * Clocks the length counters and sweeps, as well as everything clocked
+     * on a quarter frame.
+     */
+    HalfFrame,
+}
+
+/**
+ * The frame sequencer of the APU, written through $4017. In 4-step mode it
+ * raises the frame IRQ at the end of every sequence, the 5-step mode is a
+ * bit longer and has no IRQ.
+ */
+pub struct FrameCounter {
+    steps: &'static [u32; 5],
+    five_step_mode: bool,
+    irq_inhibit: bool,
+    irq: bool,
+    cycles: u32,
+    odd_cycle: bool,
+    /**
+     * CPU cycles until the sequence restarts after a write.
+     */
+    restart_delay: Option<u8>,
+    last_write: u8,
+}
+
+impl FrameCounter {
+    pub fn new() -> FrameCounter {
+        FrameCounter {
+            steps: Region::NTSC.apu_frame_steps(),
+            five_step_mode: false,
+            irq_inhibit: false,
+            irq: false,
+            cycles: 0,
+            odd_cycle: false,
+            restart_delay: None,
+            last_write: 0,
+        }
+    }
+
+    /**
+     * The mode and IRQ inhibit flags apply at once, but the sequence restarts
+     * 3 CPU cycles after a write on an APU cycle and 4 after one between APU
+     * cycles. `sub_cycle` is the cycle of the write within the instruction
+     * that has not been clocked yet.
+     */
+    pub fn write(&mut self, value: u8, sub_cycle: u8) {
+        self.last_write = value;
+        self.five_step_mode = value & 0x80 != 0;
+        self.irq_inhibit = value & 0x40 != 0;
+        if self.irq_inhibit {
+            self.irq = false;
+        }
+        let between_apu_cycles = self.odd_cycle != (sub_cycle & 0x01 != 0);
+        self.restart_delay = Some(sub_cycle + if between_apu_cycles { 4 } else { 3 });
+    }
+
+    pub fn irq(&self) -> bool {
+        self.irq
+    }
+
+    pub fn acknowledge_irq(&mut self) {
+        self.irq = false;
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.steps = region.apu_frame_steps();
+    }
+
+    /**
+     * The last value written to $4017 is written again on reset.
+     */
+    pub fn reset(&mut self) {
+        let value = self.last_write;
+        self.write(value, 0);
+    }
+
+    /**
+     * Advances the sequence by one CPU cycle.
+     */
+    pub fn clock(&mut self) -> Option<FrameClock> {
+        self.odd_cycle = !self.odd_cycle;
+        if let Some(delay) = self.restart_delay {
+            if delay <= 1 {
+                self.restart_delay = None;
+                self.cycles = 0;
+                //Switching to the 5-step mode clocks everything straight away
+                return if self.five_step_mode {
+                    Some(FrameClock::HalfFrame)
+                } else {
+                    None
+                };
+            }
+            self.restart_delay = Some(delay - 1);
+        }
+
+        self.cycles += 1;
+        //The IRQ flag is set during the last 3 cycles of the 4-step sequence
+        if !self.five_step_mode && !self.irq_inhibit && self.cycles >= self.steps[3] - 1 {
+            self.irq = true;
+        }
+        let step = self.steps.iter().position(|&step| step == self.cycles);
+        let clock = match step {
+            Some(0) | Some(2) => Some(FrameClock::QuarterFrame),
+            Some(1) | Some(4) => Some(FrameClock::HalfFrame),
+            Some(3) if !self.five_step_mode => Some(FrameClock::HalfFrame),
+            _ => None,
+        };
+        let last_step = if self.five_step_mode { 4 } else { 3 };
+        if self.cycles > self.steps[last_step] {
+            self.cycles = 0;
+        }
+        clock
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FrameClock, FrameCounter};
+    use region::Region;
+
+    fn clocks(frame_counter: &mut FrameCounter, cycles: u32) -> Vec<(u32, FrameClock)> {
+        (1..cycles + 1)
+            .filter_map(|cycle| frame_counter.clock().map(|clock| (cycle, clock)))
+            .collect()
+    }
+
+    #[test]
+    fn four_step_sequence_should_repeat_every_29830_cycles() {
+        let mut frame_counter = FrameCounter::new();
+        assert_eq!(
+            vec![
+                (7457, FrameClock::QuarterFrame),
+                (14913, FrameClock::HalfFrame),
+                (22371, FrameClock::QuarterFrame),
+                (29829, FrameClock::HalfFrame),
+                (29830 + 7457, FrameClock::QuarterFrame),
+                (29830 + 14913, FrameClock::HalfFrame),
+            ],
+            clocks(&mut frame_counter, 29830 + 20000)
+        );
+    }
+
+    #[test]
+    fn five_step_sequence_should_repeat_every_37282_cycles() {
+        let mut frame_counter = FrameCounter::new();
+        frame_counter.write(0x80, 0);
+        assert_eq!(
+            vec![
+                (3, FrameClock::HalfFrame),
+                (3 + 7457, FrameClock::QuarterFrame),
+                (3 + 14913, FrameClock::HalfFrame),
+                (3 + 22371, FrameClock::QuarterFrame),
+                (3 + 37281, FrameClock::HalfFrame),
+                (3 + 37282 + 7457, FrameClock::QuarterFrame),
+            ],
+            clocks(&mut frame_counter, 3 + 37282 + 10000)
+        );
+        assert!(!frame_counter.irq());
+    }
+
+    #[test]
+    fn pal_sequence_should_be_longer() {
+        let mut frame_counter = FrameCounter::new();
+        frame_counter.set_region(Region::PAL);
+        let half_frames: Vec<u32> = clocks(&mut frame_counter, 33254 * 2)
+            .into_iter()
+            .filter(|&(_, clock)| clock == FrameClock::HalfFrame)
+            .map(|(cycle, _)| cycle)
+            .collect();
+        assert_eq!(
+            vec![16627, 33253, 33254 + 16627, 33254 + 33253],
+            half_frames
+        );
+    }
+
+    #[test]
+    fn irq_should_be_raised_at_the_end_of_the_four_step_sequence() {
+        let mut frame_counter = FrameCounter::new();
+        clocks(&mut frame_counter, 29827);
+        assert!(!frame_counter.irq());
+        frame_counter.clock();
+        assert!(frame_counter.irq());
+
+        //Still set on the next cycle, so acknowledging too early does not help
+        frame_counter.acknowledge_irq();
+        frame_counter.clock();
+        assert!(frame_counter.irq());
+        frame_counter.acknowledge_irq();
+        frame_counter.clock();
+        assert!(frame_counter.irq());
+        frame_counter.acknowledge_irq();
+        frame_counter.clock();
+        assert!(!frame_counter.irq());
+    }
+
+    #[test]
+    fn irq_inhibit_should_clear_and_suppress_the_irq() {
+        let mut frame_counter = FrameCounter::new();
+        clocks(&mut frame_counter, 29830);
+        assert!(frame_counter.irq());
+
+        frame_counter.write(0x40, 0);
+        assert!(!frame_counter.irq());
+        clocks(&mut frame_counter, 29830 * 2);
+        assert!(!frame_counter.irq());
+    }
+
+    #[test]
+    fn restart_should_be_delayed_by_one_more_cycle_between_apu_cycles() {
+        let mut frame_counter = FrameCounter::new();
+        frame_counter.write(0x80, 2);
+        assert_eq!(
+            vec![(5, FrameClock::HalfFrame)],
+            clocks(&mut frame_counter, 10)
+        );
+
+        frame_counter.write(0x80, 1);
+        assert_eq!(
+            vec![(5, FrameClock::HalfFrame)],
+            clocks(&mut frame_counter, 10)
+        );
+
+        frame_counter.clock();
+        frame_counter.write(0x80, 1);
+        assert_eq!(
+            vec![(4, FrameClock::HalfFrame)],
+            clocks(&mut frame_counter, 10)
+        );
+    }
+
+    #[test]
+    fn reset_should_write_the_last_value_again() {
+        let mut frame_counter = FrameCounter::new();
+        frame_counter.write(0x80, 0);
+        clocks(&mut frame_counter, 1000);
+
+        frame_counter.reset();
+        assert_eq!(
+            vec![(3, FrameClock::HalfFrame)],
+            clocks(&mut frame_counter, 10)
+        );
+    }
+}
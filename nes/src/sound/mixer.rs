@@ -0,0 +1,54 @@
+/**
+ * The nonlinear DAC of the APU, the output is between 0 and about 1.
+ */
+pub fn mix(pulse1: i16, pulse2: i16, triangle: i16, noise: i16, dmc: i16) -> f32 {
+    let pulse = pulse1 + pulse2;
+    let pulse_out = if pulse == 0 {
+        0.0
+    } else {
+        95.88 / (8128.0 / pulse as f32 + 100.0)
+    };
+
+    let tnd = triangle as f32 / 8227.0 + noise as f32 / 12241.0 + dmc as f32 / 22638.0;
+    let tnd_out = if tnd == 0.0 {
+        0.0
+    } else {
+        159.79 / (1.0 / tnd + 100.0)
+    };
+    pulse_out + tnd_out
+}
+
+#[cfg(test)]
+mod test {
+    use super::mix;
+
+    fn assert_close(expected: f32, actual: f32) {
+        assert!(
+            (expected - actual).abs() < 0.0001,
+            "Expected {}, was {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn silence_should_be_0() {
+        assert_eq!(0.0, mix(0, 0, 0, 0, 0));
+    }
+
+    #[test]
+    fn mixer_should_follow_the_nonlinear_formulas() {
+        assert_close(0.01165, mix(1, 0, 0, 0, 0));
+        assert_close(0.25848, mix(15, 15, 0, 0, 0));
+        assert_close(0.01919, mix(0, 0, 1, 0, 0));
+        assert_close(0.01295, mix(0, 0, 0, 1, 0));
+        assert_close(0.57426, mix(0, 0, 0, 0, 127));
+        assert_close(0.74152, mix(0, 0, 15, 15, 127));
+    }
+
+    #[test]
+    fn channels_should_not_add_up_linearly() {
+        assert!(mix(15, 15, 0, 0, 0) < mix(15, 0, 0, 0, 0) * 2.0);
+        assert!(mix(0, 0, 15, 0, 127) < mix(0, 0, 15, 0, 0) + mix(0, 0, 0, 0, 127));
+    }
+}
@@ -1,9 +1,19 @@
 pub use self::sound::*;
 
+mod blip;
 #[cfg(test)]
 mod counter;
+pub mod dmc;
 mod envelope;
+mod filter;
+pub mod frame_counter;
 mod length_counter;
+mod mixer;
+pub mod noise;
+pub mod recorder;
 pub mod registers;
 mod sound;
 pub mod square;
+pub mod sweep;
+pub mod triangle;
+pub mod wav;
@@ -0,0 +1,170 @@
+use region::Region;
+use sound::envelope::Envelope;
+use sound::length_counter::LengthCounter;
+
+pub struct NoiseGenerator {
+    periods: &'static [u16; 16],
+    envelope: Envelope,
+    timer_set: u32,
+    timer: u32,
+    short_mode: bool,
+    shift_register: u16,
+    length: LengthCounter,
+    enabled: bool,
+    length_halted: bool,
+}
+
+impl NoiseGenerator {
+    pub fn new() -> NoiseGenerator {
+        NoiseGenerator {
+            periods: Region::NTSC.noise_periods(),
+            envelope: Envelope::constant(0),
+            timer_set: Region::NTSC.noise_periods()[0] as u32,
+            timer: 0,
+            short_mode: false,
+            shift_register: 1,
+            length: LengthCounter::default(),
+            enabled: false,
+            length_halted: false,
+        }
+    }
+
+    pub fn volume(&mut self, volume: u8) {
+        self.envelope = Envelope::constant(volume);
+    }
+
+    pub fn decaying_volume(&mut self, volume: u8) {
+        self.envelope = Envelope::decaying(volume);
+    }
+
+    pub fn halt_length(&mut self, halted: bool) {
+        self.length_halted = halted;
+        self.length.halt(halted);
+    }
+
+    /**
+     * Short mode takes the feedback from bit 6 instead of bit 1, which gives a
+     * metallic 93 step sequence instead of hiss.
+     */
+    pub fn period(&mut self, short_mode: bool, period_index: u8) {
+        self.short_mode = short_mode;
+        self.timer_set = self.periods[(period_index & 0x0F) as usize] as u32;
+    }
+
+    pub fn length(&mut self, length: u8) {
+        if self.enabled {
+            self.length = LengthCounter::new(length);
+            self.length.halt(self.length_halted);
+        }
+    }
+
+    /**
+     * Disabling the channel clears its length counter, which then stays at 0
+     * until the channel is enabled again.
+     */
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.length.clear();
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.length.value() > 0
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.periods = region.noise_periods();
+    }
+
+    pub fn silence(&mut self) {
+        self.set_enabled(false);
+        self.envelope = Envelope::constant(0);
+    }
+
+    pub fn quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    pub fn update(&mut self, cpu_cycles: u8) {
+        self.timer += cpu_cycles as u32;
+        while self.timer >= self.timer_set {
+            self.timer -= self.timer_set;
+            self.shift();
+        }
+    }
+
+    fn shift(&mut self) {
+        let other_bit = if self.short_mode { 6 } else { 1 };
+        let feedback = (self.shift_register ^ (self.shift_register >> other_bit)) & 0x01;
+        self.shift_register = (self.shift_register >> 1) | (feedback << 14);
+    }
+
+    pub fn noise_value(&self) -> i16 {
+        if self.length.value() > 0 && self.shift_register & 0x01 == 0 {
+            self.envelope.value() as i16
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NoiseGenerator;
+    use region::Region;
+
+    fn sequence_length(short_mode: bool) -> usize {
+        let mut noise = NoiseGenerator::new();
+        noise.period(short_mode, 0);
+        let start = noise.shift_register;
+        let mut steps = 0;
+        loop {
+            noise.shift();
+            steps += 1;
+            if noise.shift_register == start {
+                return steps;
+            }
+        }
+    }
+
+    #[test]
+    fn shift_register_should_repeat_after_32767_steps() {
+        assert_eq!(32767, sequence_length(false));
+    }
+
+    #[test]
+    fn short_mode_should_repeat_after_93_steps() {
+        assert_eq!(93, sequence_length(true));
+    }
+
+    #[test]
+    fn shift_register_should_be_clocked_with_the_period_of_the_region() {
+        let mut noise = NoiseGenerator::new();
+        noise.set_region(Region::PAL);
+        noise.period(false, 2);
+
+        noise.update(13);
+        assert_eq!(1, noise.shift_register);
+        noise.update(1);
+        assert_eq!(0x4000, noise.shift_register);
+    }
+
+    #[test]
+    fn output_should_follow_bit_0_and_length() {
+        let mut noise = NoiseGenerator::new();
+        noise.set_enabled(true);
+        noise.volume(9);
+        assert_eq!(0, noise.noise_value());
+
+        noise.length(1);
+        noise.shift_register = 0x02;
+        assert_eq!(9, noise.noise_value());
+        noise.shift_register = 0x03;
+        assert_eq!(0, noise.noise_value());
+    }
+}
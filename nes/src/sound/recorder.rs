@@ -0,0 +1,256 @@
+use sound::wav::WavWriter;
+use sound::AudioDevice;
+use std::cell::RefCell;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+
+/**
+ * Names added to the file name of the recording for the file of each channel,
+ * in the order of `AudioDevice::play_stems`.
+ */
+pub const STEM_NAMES: [&str; 5] = ["pulse1", "pulse2", "triangle", "noise", "dmc"];
+
+type FileWriter = WavWriter<BufWriter<File>>;
+
+struct Recording {
+    mixed: FileWriter,
+    stems: Vec<FileWriter>,
+}
+
+impl Recording {
+    /**
+     * Fills in the sizes of every file, returns the first error.
+     */
+    fn finish(self) -> io::Result<()> {
+        let mut result = self.mixed.finish().map(|_| ());
+        for stem in self.stems.into_iter() {
+            let stem_result = stem.finish().map(|_| ());
+            result = result.and(stem_result);
+        }
+        result
+    }
+
+    /**
+     * Removes the files of a recording that could not be started.
+     */
+    fn discard(self, path: &str) {
+        let stem_count = self.stems.len();
+        drop(self);
+        let _ = fs::remove_file(path);
+        for name in STEM_NAMES[..stem_count].iter() {
+            let _ = fs::remove_file(stem_path(path, name));
+        }
+    }
+}
+
+/**
+ * Audio device passing everything to another one, writing it to WAV files
+ * while recording.
+ */
+pub struct WavRecorder<A: AudioDevice> {
+    audio_device: A,
+    recording: RefCell<Option<Recording>>,
+}
+
+impl<A: AudioDevice> WavRecorder<A> {
+    pub fn new(audio_device: A) -> WavRecorder<A> {
+        WavRecorder {
+            audio_device: audio_device,
+            recording: RefCell::new(None),
+        }
+    }
+
+    /**
+     * Records the output to `path`, and with `stems` each channel to a file
+     * named after the channel next to it. Stops any recording going on. The
+     * files already created are removed if one of them cannot be.
+     */
+    pub fn start(&self, path: &str, stems: bool) -> io::Result<()> {
+        self.stop()?;
+        let sample_rate = self.audio_device.sample_rate();
+        let mut recording = Recording {
+            mixed: WavWriter::create(path, sample_rate)?,
+            stems: vec![],
+        };
+        if stems {
+            for name in STEM_NAMES.iter() {
+                match WavWriter::create(&stem_path(path, name), sample_rate) {
+                    Ok(stem) => recording.stems.push(stem),
+                    Err(error) => {
+                        recording.discard(path);
+                        return Err(error);
+                    }
+                }
+            }
+        }
+        *self.recording.borrow_mut() = Some(recording);
+        Ok(())
+    }
+
+    pub fn stop(&self) -> io::Result<()> {
+        match self.recording.borrow_mut().take() {
+            Some(recording) => recording.finish(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.borrow().is_some()
+    }
+
+    /**
+     * Writes to the recording if there is one. The audio keeps playing when
+     * that fails, so the recording is stopped, keeping what was written, and
+     * the error printed.
+     */
+    fn record<F: FnOnce(&mut Recording) -> io::Result<()>>(&self, write: F) {
+        let mut recording = self.recording.borrow_mut();
+        let result = match recording.as_mut() {
+            Some(recording) => write(recording),
+            None => return,
+        };
+        if let Err(error) = result {
+            eprintln!("Recording stopped: {}", error);
+            if let Err(error) = recording.take().unwrap().finish() {
+                eprintln!("Could not finish the recording: {}", error);
+            }
+        }
+    }
+}
+
+/**
+ * `path` with `-name` before the extension.
+ */
+pub fn stem_path(path: &str, name: &str) -> String {
+    let file_start = path.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match path[file_start..].rfind('.') {
+        Some(i) if i > 0 => format!(
+            "{}-{}{}",
+            &path[..file_start + i],
+            name,
+            &path[file_start + i..]
+        ),
+        _ => format!("{}-{}", path, name),
+    }
+}
+
+impl<A: AudioDevice> AudioDevice for WavRecorder<A> {
+    fn play(&self, samples: &[i16]) {
+        self.record(|recording| recording.mixed.write(samples));
+        self.audio_device.play(samples);
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.audio_device.sample_rate()
+    }
+
+    fn buffered_samples(&self) -> Option<usize> {
+        self.audio_device.buffered_samples()
+    }
+
+    fn wants_stems(&self) -> bool {
+        self.recording
+            .borrow()
+            .as_ref()
+            .map(|recording| !recording.stems.is_empty())
+            .unwrap_or(false)
+    }
+
+    fn play_stems(&self, stems: &[Vec<i16>]) {
+        self.record(|recording| {
+            for (writer, samples) in recording.stems.iter_mut().zip(stems.iter()) {
+                writer.write(samples)?;
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{stem_path, WavRecorder};
+    use sound::AudioDevice;
+    use std::cell::RefCell;
+    use std::env;
+    use std::fs;
+    use std::rc::Rc;
+
+    #[test]
+    fn stem_name_should_go_before_the_extension() {
+        assert_eq!("/tmp/song-dmc.wav", stem_path("/tmp/song.wav", "dmc"));
+        assert_eq!("a.b/song-dmc", stem_path("a.b/song", "dmc"));
+        assert_eq!(".wav-dmc", stem_path(".wav", "dmc"));
+    }
+
+    #[test]
+    fn samples_should_be_played_and_recorded_until_stopped() {
+        let path = env::temp_dir().join("rustiness-recorder-test.wav");
+        let path = path.to_str().unwrap();
+        let played = Rc::new(RefCell::new(vec![]));
+        let recorder = WavRecorder::new(played.clone());
+
+        recorder.play(&[1]);
+        recorder.start(path, false).unwrap();
+        assert!(recorder.is_recording());
+        assert!(!recorder.wants_stems());
+        recorder.play(&[2, 3]);
+        recorder.stop().unwrap();
+        recorder.play(&[4]);
+
+        assert_eq!(vec![1, 2, 3, 4], *played.borrow());
+        let wav = fs::read(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(&[2, 0, 3, 0], &wav[44..]);
+    }
+
+    #[test]
+    fn stems_should_be_recorded_to_a_file_per_channel() {
+        let path = env::temp_dir().join("rustiness-stems-test.wav");
+        let path = path.to_str().unwrap();
+        let recorder = WavRecorder::new(Rc::new(RefCell::new(vec![])));
+        recorder.start(path, true).unwrap();
+        assert!(recorder.wants_stems());
+        recorder.play_stems(&[vec![1], vec![2], vec![3], vec![4], vec![5]]);
+        recorder.stop().unwrap();
+
+        fs::remove_file(path).unwrap();
+        let dmc_path = stem_path(path, "dmc");
+        let wav = fs::read(&dmc_path).unwrap();
+        assert_eq!(&[5, 0], &wav[44..]);
+        for name in ["pulse1", "pulse2", "triangle", "noise", "dmc"].iter() {
+            fs::remove_file(stem_path(path, name)).unwrap();
+        }
+    }
+
+    #[test]
+    fn files_should_be_removed_if_a_stem_cannot_be_created() {
+        let path = env::temp_dir().join("rustiness-failed-stems-test.wav");
+        let path = path.to_str().unwrap();
+        let noise_path = stem_path(path, "noise");
+        fs::create_dir_all(&noise_path).unwrap();
+        let recorder = WavRecorder::new(Rc::new(RefCell::new(vec![])));
+
+        let result = recorder.start(path, true);
+        fs::remove_dir(&noise_path).unwrap();
+        assert!(result.is_err());
+        assert!(!recorder.is_recording());
+        assert!(fs::metadata(path).is_err());
+        for name in ["pulse1", "pulse2", "triangle", "dmc"].iter() {
+            assert!(fs::metadata(stem_path(path, name)).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn failed_write_should_stop_the_recording_but_not_the_audio() {
+        let played = Rc::new(RefCell::new(vec![]));
+        let recorder = WavRecorder::new(played.clone());
+        recorder.start("/dev/full", false).unwrap();
+        recorder.play(&[1; 10_000]);
+
+        assert!(!recorder.is_recording());
+        assert_eq!(10_000, played.borrow().len());
+    }
+}
@@ -1,37 +1,112 @@
+use super::dmc::DeltaModulationChannel;
+use super::frame_counter::FrameCounter;
+use super::noise::NoiseGenerator;
 use super::square::PulseGenerator;
+use super::triangle::TriangleGenerator;
+use super::{AudioDevice, APU};
 use memory::{Memory, MemoryMappedIO};
 use std::cell::RefCell;
 use std::rc::Rc;
 
 pub struct Register1(pub Rc<RefCell<PulseGenerator>>);
+pub struct Register2(pub Rc<RefCell<PulseGenerator>>);
 pub struct Register3(pub Rc<RefCell<PulseGenerator>>);
 pub struct Register4(pub Rc<RefCell<PulseGenerator>>);
 
+pub struct TriangleRegister1(pub Rc<RefCell<TriangleGenerator>>);
+pub struct TriangleRegister3(pub Rc<RefCell<TriangleGenerator>>);
+pub struct TriangleRegister4(pub Rc<RefCell<TriangleGenerator>>);
+
+pub struct NoiseRegister1(pub Rc<RefCell<NoiseGenerator>>);
+pub struct NoiseRegister3(pub Rc<RefCell<NoiseGenerator>>);
+pub struct NoiseRegister4(pub Rc<RefCell<NoiseGenerator>>);
+
+pub struct DMCRegister1(pub Rc<RefCell<DeltaModulationChannel>>);
+pub struct DMCRegister2(pub Rc<RefCell<DeltaModulationChannel>>);
+pub struct DMCRegister3(pub Rc<RefCell<DeltaModulationChannel>>);
+pub struct DMCRegister4(pub Rc<RefCell<DeltaModulationChannel>>);
+
+pub struct FrameCounterRegister(pub Rc<RefCell<FrameCounter>>);
+
+/**
+ * $4015, enables the channels and reports which of them are still playing
+ * along with the pending IRQs.
+ */
+pub struct StatusRegister {
+    square1: Rc<RefCell<PulseGenerator>>,
+    square2: Rc<RefCell<PulseGenerator>>,
+    triangle: Rc<RefCell<TriangleGenerator>>,
+    noise: Rc<RefCell<NoiseGenerator>>,
+    dmc: Rc<RefCell<DeltaModulationChannel>>,
+    frame_counter: Rc<RefCell<FrameCounter>>,
+}
+
+impl StatusRegister {
+    pub fn new<T: AudioDevice>(apu: &APU<T>) -> StatusRegister {
+        StatusRegister {
+            square1: apu.square1(),
+            square2: apu.square2(),
+            triangle: apu.triangle(),
+            noise: apu.noise(),
+            dmc: apu.dmc(),
+            frame_counter: apu.frame_counter(),
+        }
+    }
+}
+
 impl MemoryMappedIO for Register1 {
     fn read(&self, _: &dyn Memory) -> u8 {
-        unimplemented!();
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
     }
 
     fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        let mut pulse = self.0.borrow_mut();
+        pulse.duty(value >> 6);
+        pulse.halt_length(value & 0x20 > 0);
         if value & 0x10 > 0 {
-            self.0.borrow_mut().volume(value & 0xF);
+            pulse.volume(value & 0xF);
         } else {
-            self.0.borrow_mut().decaying_volume(value & 0xF);
+            pulse.decaying_volume(value & 0xF);
         }
     }
 }
+impl MemoryMappedIO for Register2 {
+    fn read(&self, _: &dyn Memory) -> u8 {
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        self.0.borrow_mut().sweep(value);
+    }
+}
 impl MemoryMappedIO for Register3 {
     fn read(&self, _: &dyn Memory) -> u8 {
         0
     }
 
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
     fn write(&mut self, _: &mut dyn Memory, value: u8) {
         self.0.borrow_mut().timer_low(value);
     }
 }
 impl MemoryMappedIO for Register4 {
     fn read(&self, _: &dyn Memory) -> u8 {
-        unimplemented!();
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
     }
 
     fn write(&mut self, _: &mut dyn Memory, value: u8) {
@@ -40,31 +115,261 @@ impl MemoryMappedIO for Register4 {
     }
 }
 
+impl MemoryMappedIO for TriangleRegister1 {
+    fn read(&self, _: &dyn Memory) -> u8 {
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        self.0
+            .borrow_mut()
+            .linear_counter(value & 0x80 > 0, value & 0x7F);
+    }
+}
+impl MemoryMappedIO for TriangleRegister3 {
+    fn read(&self, _: &dyn Memory) -> u8 {
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        self.0.borrow_mut().timer_low(value);
+    }
+}
+impl MemoryMappedIO for TriangleRegister4 {
+    fn read(&self, _: &dyn Memory) -> u8 {
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        self.0.borrow_mut().length(value >> 3);
+        self.0.borrow_mut().timer_high(value & 0x07);
+    }
+}
+
+impl MemoryMappedIO for NoiseRegister1 {
+    fn read(&self, _: &dyn Memory) -> u8 {
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        let mut noise = self.0.borrow_mut();
+        noise.halt_length(value & 0x20 > 0);
+        if value & 0x10 > 0 {
+            noise.volume(value & 0xF);
+        } else {
+            noise.decaying_volume(value & 0xF);
+        }
+    }
+}
+impl MemoryMappedIO for NoiseRegister3 {
+    fn read(&self, _: &dyn Memory) -> u8 {
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        self.0.borrow_mut().period(value & 0x80 > 0, value & 0x0F);
+    }
+}
+impl MemoryMappedIO for NoiseRegister4 {
+    fn read(&self, _: &dyn Memory) -> u8 {
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        self.0.borrow_mut().length(value >> 3);
+    }
+}
+
+impl MemoryMappedIO for DMCRegister1 {
+    fn read(&self, _: &dyn Memory) -> u8 {
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        self.0
+            .borrow_mut()
+            .flags_and_rate(value & 0x80 > 0, value & 0x40 > 0, value & 0x0F);
+    }
+}
+impl MemoryMappedIO for DMCRegister2 {
+    fn read(&self, _: &dyn Memory) -> u8 {
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        self.0.borrow_mut().direct_load(value);
+    }
+}
+impl MemoryMappedIO for DMCRegister3 {
+    fn read(&self, _: &dyn Memory) -> u8 {
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        self.0.borrow_mut().sample_address_register(value);
+    }
+}
+impl MemoryMappedIO for DMCRegister4 {
+    fn read(&self, _: &dyn Memory) -> u8 {
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        self.0.borrow_mut().sample_length_register(value);
+    }
+}
+
+impl MemoryMappedIO for FrameCounterRegister {
+    fn read(&self, _: &dyn Memory) -> u8 {
+        0
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, memory: &mut dyn Memory, value: u8) {
+        self.write_at_cycle(memory, value, 0);
+    }
+
+    fn write_at_cycle(&mut self, _: &mut dyn Memory, value: u8, sub_cycle: u8) {
+        self.0.borrow_mut().write(value, sub_cycle);
+    }
+}
+
+impl MemoryMappedIO for StatusRegister {
+    /**
+     * Reading acknowledges the frame IRQ, but not the DMC one.
+     */
+    fn read(&self, _: &dyn Memory) -> u8 {
+        let mut status = 0;
+        if self.square1.borrow().is_active() {
+            status |= 0x01;
+        }
+        if self.square2.borrow().is_active() {
+            status |= 0x02;
+        }
+        if self.triangle.borrow().is_active() {
+            status |= 0x04;
+        }
+        if self.noise.borrow().is_active() {
+            status |= 0x08;
+        }
+        if self.dmc.borrow().is_active() {
+            status |= 0x10;
+        }
+
+        let mut frame_counter = self.frame_counter.borrow_mut();
+        if frame_counter.irq() {
+            status |= 0x40;
+            frame_counter.acknowledge_irq();
+        }
+        if self.dmc.borrow().irq() {
+            status |= 0x80;
+        }
+        status
+    }
+
+    fn open_bus_mask(&self) -> u8 {
+        0x20
+    }
+
+    /**
+     * Writing acknowledges the DMC IRQ.
+     */
+    fn write(&mut self, _: &mut dyn Memory, value: u8) {
+        self.square1.borrow_mut().set_enabled(value & 0x01 != 0);
+        self.square2.borrow_mut().set_enabled(value & 0x02 != 0);
+        self.triangle.borrow_mut().set_enabled(value & 0x04 != 0);
+        self.noise.borrow_mut().set_enabled(value & 0x08 != 0);
+        self.dmc.borrow_mut().set_enabled(value & 0x10 != 0);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use memory::{BasicMemory, CPUMemory, Memory};
+    use region::Region;
     use sound::square::PulseGenerator;
-    use sound::AudioDevice;
+    use sound::{AudioDevice, APU};
     use std::cell::RefCell;
 
     use sound::counter::ClockTester;
 
+    type TestAPU = APU<RefCell<Vec<i16>>>;
+
+    /**
+     * Frame counter clocks of the 4-step sequence in the first `cycles` CPU cycles.
+     */
+    fn frame_clocks(cycles: u64, half_frames: bool) -> u64 {
+        let steps = Region::NTSC.apu_frame_steps();
+        let sequence = steps[3] as u64 + 1;
+        let step_clocks = steps[0..4]
+            .iter()
+            .enumerate()
+            .filter(|&(step, _)| !half_frames || step % 2 == 1)
+            .filter(|&(_, &step)| cycles % sequence >= step as u64)
+            .count() as u64;
+        (cycles / sequence) * if half_frames { 2 } else { 4 } + step_clocks
+    }
+
     #[test]
     fn simple_constant_square_wave() {
-        let generator = Rc::new(RefCell::new(PulseGenerator::new()));
-        let mut cpu_memory = cpu_memory(generator.clone());
-        cpu_memory.set(0x4000, 0x1A, 0);
+        let apu = APU::new(RefCell::new(vec![]), 1.0);
+        let mut cpu_memory = cpu_memory(&apu);
+        cpu_memory.set(0x4015, 0x01, 0);
+        cpu_memory.set(0x4000, 0x9A, 0);
         cpu_memory.set(0x4002, 0xAA, 0);
         cpu_memory.set(0x4003, 0b0100_1001, 0);
 
-        assert_eq!(generator.borrow().pulse_value(), 0);
-        let mut clock = ClockTester::new(generator, 426 * 2);
+        assert_eq!(apu.square1().borrow().pulse_value(), 0);
+        let mut clock = ClockTester::new(apu, 426 * 2);
         {
             clock.count_down(
-                |gen, tick| gen.borrow_mut().update(tick),
-                &|gen, cycles| assert_eq!(gen.borrow().pulse_value(), 0, "After {} cycles", cycles),
-                &|gen, _| assert_eq!(gen.borrow().pulse_value(), 10),
+                |apu, tick| apu.update(tick),
+                &|apu, cycles| assert_eq!(pulse_value(apu), 0, "After {} cycles", cycles),
+                &|apu, _| assert_eq!(pulse_value(apu), 10),
             );
         }
 
@@ -72,7 +377,11 @@ mod test {
             execute_one_cycle(&mut clock, &|gen, cycles| {
                 assert_eq!(
                     gen.pulse_value(),
-                    if cycles >= 14913 * 8 { 0 } else { 10 },
+                    if frame_clocks(cycles, true) >= 8 {
+                        0
+                    } else {
+                        10
+                    },
                     "After {} cycles",
                     cycles
                 )
@@ -82,21 +391,21 @@ mod test {
 
     #[test]
     fn simple_decaying_square_wave() {
-        let generator = Rc::new(RefCell::new(PulseGenerator::new()));
-        let mut cpu_memory = cpu_memory(generator.clone());
-        cpu_memory.set(0x4000, 4, 0);
+        let apu = APU::new(RefCell::new(vec![]), 1.0);
+        let mut cpu_memory = cpu_memory(&apu);
+        cpu_memory.set(0x4015, 0x01, 0);
+        cpu_memory.set(0x4000, 0x84, 0);
         cpu_memory.set(0x4002, 0xAA, 0);
         cpu_memory.set(0x4003, 0b0000_1001, 0);
-        let decaying_period = 14913 * 5;
-        let mut clock = ClockTester::new(generator, 426 * 2);
+        let mut clock = ClockTester::new(apu, 426 * 2);
         {
             clock.count_down(
-                |gen, tick| gen.borrow_mut().update(tick),
-                &|gen, cycles| assert_eq!(gen.borrow().pulse_value(), 0, "After {} cycles", cycles),
-                &|gen, cycles| {
+                |apu, tick| apu.update(tick),
+                &|apu, cycles| assert_eq!(pulse_value(apu), 0, "After {} cycles", cycles),
+                &|apu, cycles| {
                     assert_eq!(
-                        gen.borrow().pulse_value(),
-                        15 - (cycles / decaying_period) as i16
+                        pulse_value(apu),
+                        15 - (frame_clocks(cycles, false) / 5) as i16
                     )
                 },
             );
@@ -106,7 +415,7 @@ mod test {
             execute_one_cycle(&mut clock, &|gen, cycles| {
                 assert_eq!(
                     gen.pulse_value(),
-                    cmp::max(0, 15 - (cycles / decaying_period) as i16),
+                    cmp::max(0, 15 - (frame_clocks(cycles, false) / 5) as i16),
                     "After {} cycles",
                     cycles
                 )
@@ -114,18 +423,133 @@ mod test {
         }
     }
 
+    #[test]
+    fn duty_should_select_the_pulse_sequence() {
+        for &(duty, expected) in [
+            (0x00, [0, 10, 0, 0, 0, 0, 0, 0]),
+            (0x40, [0, 10, 10, 0, 0, 0, 0, 0]),
+            (0x80, [0, 10, 10, 10, 10, 0, 0, 0]),
+            (0xC0, [10, 0, 0, 10, 10, 10, 10, 10]),
+        ]
+        .iter()
+        {
+            let mut apu = APU::new(RefCell::new(vec![]), 1.0);
+            let mut cpu_memory = cpu_memory(&apu);
+            cpu_memory.set(0x4015, 0x01, 0);
+            cpu_memory.set(0x4000, duty | 0x1A, 0);
+            cpu_memory.set(0x4002, 0x10, 0);
+            cpu_memory.set(0x4003, 0x08, 0);
+
+            let mut values = [0; 8];
+            for value in values.iter_mut() {
+                *value = pulse_value(&apu);
+                apu.update(0x20);
+            }
+            assert_eq!(expected, values, "Duty {:02X}", duty);
+        }
+    }
+
+    #[test]
+    fn sweep_should_mute_periods_out_of_range() {
+        let apu = APU::new(RefCell::new(vec![]), 1.0);
+        let mut cpu_memory = cpu_memory(&apu);
+        cpu_memory.set(0x4015, 0x01, 0);
+        cpu_memory.set(0x4000, 0xDA, 0);
+        cpu_memory.set(0x4002, 0x00, 0);
+        cpu_memory.set(0x4003, 0x0C, 0);
+        assert_eq!(0, pulse_value(&apu));
+
+        cpu_memory.set(0x4001, 0x08, 0);
+        assert_eq!(10, pulse_value(&apu));
+    }
+
+    #[test]
+    fn status_should_show_the_channels_with_a_length_left() {
+        let apu = APU::new(RefCell::new(vec![]), 1.0);
+        let mut cpu_memory = cpu_memory(&apu);
+        cpu_memory.set(0x4015, 0x1D, 0);
+        cpu_memory.set(0x4003, 0x08, 0);
+        cpu_memory.set(0x400B, 0x08, 0);
+        cpu_memory.set(0x400F, 0x08, 0);
+        assert_eq!(0x1D, cpu_memory.get(0x4015, 0) & 0xDF);
+
+        cpu_memory.set(0x4015, 0x10, 0);
+        assert_eq!(0x10, cpu_memory.get(0x4015, 0) & 0xDF);
+    }
+
+    #[test]
+    fn lengths_should_be_ignored_while_the_channel_is_disabled() {
+        let apu = APU::new(RefCell::new(vec![]), 1.0);
+        let mut cpu_memory = cpu_memory(&apu);
+        cpu_memory.set(0x4003, 0x08, 0);
+        assert_eq!(0, cpu_memory.get(0x4015, 0) & 0xDF);
+
+        cpu_memory.set(0x4015, 0x01, 0);
+        assert_eq!(0, cpu_memory.get(0x4015, 0) & 0xDF);
+        cpu_memory.set(0x4003, 0x08, 0);
+        assert_eq!(0x01, cpu_memory.get(0x4015, 0) & 0xDF);
+    }
+
+    #[test]
+    fn reading_status_should_acknowledge_the_frame_irq() {
+        let mut apu = APU::new(RefCell::new(vec![]), 1.0);
+        let cpu_memory = cpu_memory(&apu);
+        for _ in 0..29830 {
+            apu.update(1);
+        }
+        assert!(apu.irq());
+        assert_eq!(0x40, cpu_memory.get(0x4015, 0) & 0xDF);
+        assert!(!apu.irq());
+        assert_eq!(0x00, cpu_memory.get(0x4015, 0) & 0xDF);
+    }
+
+    #[test]
+    fn writing_status_should_acknowledge_the_dmc_irq() {
+        let apu = APU::new(RefCell::new(vec![]), 1.0);
+        let mut cpu_memory = cpu_memory(&apu);
+        cpu_memory.set(0x4017, 0x40, 0);
+        cpu_memory.set(0x4010, 0x80, 0);
+        cpu_memory.set(0x4013, 0x00, 0);
+        cpu_memory.set(0x4015, 0x10, 0);
+        assert_eq!(0x10, cpu_memory.get(0x4015, 0) & 0xDF);
+
+        apu.dmc().borrow_mut().load_sample(0);
+        assert_eq!(0x80, cpu_memory.get(0x4015, 0) & 0xDF);
+        assert_eq!(0x80, cpu_memory.get(0x4015, 0) & 0xDF);
+
+        cpu_memory.set(0x4015, 0x00, 0);
+        assert!(!apu.irq());
+    }
+
+    #[test]
+    fn status_bit_5_should_be_open_bus() {
+        let apu = APU::new(RefCell::new(vec![]), 1.0);
+        let mut cpu_memory = cpu_memory(&apu);
+        cpu_memory.set(0x4015, 0x00, 0);
+        assert_eq!(0x00, cpu_memory.get(0x4015, 0));
+        cpu_memory.set(0x0000, 0xFF, 0);
+        assert_eq!(0x20, cpu_memory.get(0x4015, 0));
+    }
+
     impl AudioDevice for RefCell<Vec<i16>> {
         fn play(&self, pulse: &[i16]) {
             push_all(self.borrow_mut().as_mut(), pulse);
         }
     }
 
-    fn cpu_memory(square1: Rc<RefCell<PulseGenerator>>) -> CPUMemory<'static> {
+    fn cpu_memory(apu: &TestAPU) -> CPUMemory<'static> {
         cpu_memory!(
             box BasicMemory::new(),
-            0x4000 => MutableRef::Box(box Register1(square1.clone())),
-            0x4002 => MutableRef::Box(box Register3(square1.clone())),
-            0x4003 => MutableRef::Box(box Register4(square1.clone()))
+            0x4000 => MutableRef::Box(box Register1(apu.square1())),
+            0x4001 => MutableRef::Box(box Register2(apu.square1())),
+            0x4002 => MutableRef::Box(box Register3(apu.square1())),
+            0x4003 => MutableRef::Box(box Register4(apu.square1())),
+            0x400B => MutableRef::Box(box TriangleRegister4(apu.triangle())),
+            0x400F => MutableRef::Box(box NoiseRegister4(apu.noise())),
+            0x4010 => MutableRef::Box(box DMCRegister1(apu.dmc())),
+            0x4013 => MutableRef::Box(box DMCRegister4(apu.dmc())),
+            0x4015 => MutableRef::Box(box StatusRegister::new(apu)),
+            0x4017 => MutableRef::Box(box FrameCounterRegister(apu.frame_counter()))
         )
     }
 
@@ -135,40 +559,40 @@ mod test {
         }
     }
 
-    fn execute_one_cycle<F>(
-        clock: &mut ClockTester<Rc<RefCell<PulseGenerator>>>,
-        assert_value_high: &F,
-    ) where
+    fn pulse_value(apu: &TestAPU) -> i16 {
+        apu.square1().borrow().pulse_value()
+    }
+
+    fn execute_one_cycle<F>(clock: &mut ClockTester<TestAPU>, assert_value_high: &F)
+    where
         F: Fn(&PulseGenerator, u64),
     {
         {
             for _ in 0..3 {
                 clock.count_down(
-                    |gen, tick| gen.borrow_mut().update(tick),
-                    &|gen, cycles| assert_value_high(&gen.borrow(), cycles),
-                    &|gen, cycles| assert_value_high(&gen.borrow(), cycles),
+                    |apu, tick| apu.update(tick),
+                    &|apu, cycles| assert_value_high(&apu.square1().borrow(), cycles),
+                    &|apu, cycles| assert_value_high(&apu.square1().borrow(), cycles),
                 );
             }
             clock.count_down(
-                |gen, tick| gen.borrow_mut().update(tick),
-                &|gen, cycles| assert_value_high(&gen.borrow(), cycles),
-                &|gen, cycles| assert_eq!(gen.borrow().pulse_value(), 0, "After {} cycles", cycles),
+                |apu, tick| apu.update(tick),
+                &|apu, cycles| assert_value_high(&apu.square1().borrow(), cycles),
+                &|apu, cycles| assert_eq!(pulse_value(apu), 0, "After {} cycles", cycles),
             );
         }
         {
             for _ in 0..3 {
                 clock.count_down(
-                    |gen, tick| gen.borrow_mut().update(tick),
-                    &|gen, cycles| {
-                        assert_eq!(gen.borrow().pulse_value(), 0, "After {} cycles", cycles)
-                    },
-                    &|gen, _| assert_eq!(gen.borrow().pulse_value(), 0),
+                    |apu, tick| apu.update(tick),
+                    &|apu, cycles| assert_eq!(pulse_value(apu), 0, "After {} cycles", cycles),
+                    &|apu, _| assert_eq!(pulse_value(apu), 0),
                 );
             }
             clock.count_down(
-                |gen, tick| gen.borrow_mut().update(tick),
-                &|gen, cycles| assert_eq!(gen.borrow().pulse_value(), 0, "After {} cycles", cycles),
-                &|gen, cycles| assert_value_high(&gen.borrow(), cycles),
+                |apu, tick| apu.update(tick),
+                &|apu, cycles| assert_eq!(pulse_value(apu), 0, "After {} cycles", cycles),
+                &|apu, cycles| assert_value_high(&apu.square1().borrow(), cycles),
             );
         }
     }
@@ -1,26 +1,169 @@
+use region::Region;
+use sound::blip::BlipBuffer;
+use sound::dmc;
+use sound::filter::Filter;
+use sound::frame_counter::{FrameClock, FrameCounter};
+use sound::mixer;
+use sound::noise;
 use sound::square;
+use sound::sweep::Negate;
+use sound::triangle;
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/**
+ * CPU cycles between two batches of samples sent to the audio device.
+ */
+const AUDIO_FRAME_CYCLES: u32 = 2048;
+/**
+ * Largest change of the resampling rate used to keep the audio queue of a
+ * paced device at its target level.
+ */
+const MAX_RATE_ADJUSTMENT: f64 = 0.005;
+/**
+ * Longest wait for the queue of a paced device to drain, about a frame, before
+ * the emulation falls back to pacing itself by the clock.
+ */
+const MAX_PACING_WAIT_MILLIS: u64 = 20;
 
 pub trait AudioDevice {
     fn play(&self, &[i16]);
+
+    /**
+     * Samples per second the device plays.
+     */
+    fn sample_rate(&self) -> u32 {
+        48_000
+    }
+
+    /**
+     * Samples queued but not played yet, if the device can tell.
+     */
+    fn buffered_samples(&self) -> Option<usize> {
+        None
+    }
+
+    /**
+     * Whether `play_stems` should be called as well.
+     */
+    fn wants_stems(&self) -> bool {
+        false
+    }
+
+    /**
+     * The samples of each channel on its own, pulse 1, pulse 2, triangle,
+     * noise and DMC, played along with `play`.
+     */
+    fn play_stems(&self, _: &[Vec<i16>]) {}
+}
+
+/**
+ * A signal resampled to the sample rate and filtered like the output of the
+ * console.
+ */
+struct Output {
+    level: f32,
+    blip: BlipBuffer,
+    filters: Vec<Filter>,
+    samples: Vec<f32>,
+}
+
+impl Output {
+    fn new(clock_rate: u32, sample_rate: u32) -> Output {
+        Output {
+            level: 0.0,
+            blip: BlipBuffer::new(clock_rate, sample_rate),
+            filters: vec![
+                Filter::high_pass(90.0, sample_rate),
+                Filter::high_pass(440.0, sample_rate),
+                Filter::low_pass(14_000.0, sample_rate),
+            ],
+            samples: vec![],
+        }
+    }
+
+    fn set_level(&mut self, cycles: u32, level: f32) {
+        if level != self.level {
+            self.blip.add_delta(cycles, level - self.level);
+            self.level = level;
+        }
+    }
+
+    /**
+     * Ends the frame after `cycles` and returns the samples in it, scaled so
+     * that a level of 1 is `scale`.
+     */
+    fn end_frame(&mut self, cycles: u32, scale: f32) -> Vec<i16> {
+        self.blip.end_frame(cycles);
+        self.samples.clear();
+        self.blip.read_samples(&mut self.samples);
+        let filters = &mut self.filters;
+        self.samples
+            .iter()
+            .map(|&sample| {
+                let filtered = filters
+                    .iter_mut()
+                    .fold(sample, |sample, filter| filter.apply(sample));
+                (filtered * scale)
+                    .max(i16::min_value() as f32)
+                    .min(i16::max_value() as f32) as i16
+            })
+            .collect()
+    }
 }
 
 pub struct APU<T: AudioDevice> {
     audio_device: T,
-    volume_scale: i16,
+    volume: f32,
     square1: Rc<RefCell<square::PulseGenerator>>,
     square2: Rc<RefCell<square::PulseGenerator>>,
+    triangle: Rc<RefCell<triangle::TriangleGenerator>>,
+    noise: Rc<RefCell<noise::NoiseGenerator>>,
+    dmc: Rc<RefCell<dmc::DeltaModulationChannel>>,
+    frame_counter: Rc<RefCell<FrameCounter>>,
+    clock_rate: u32,
+    output: Output,
+    /**
+     * Each channel mixed on its own, while the audio device wants them.
+     */
+    stems: Option<Vec<Output>>,
+    /**
+     * Samples to keep queued when the audio device sets the speed.
+     */
+    pacing_latency: Option<usize>,
+    /**
+     * Set when the queue of the paced device stopped draining, until it does.
+     */
+    pacing_stalled: bool,
     cpu_cycles: u32,
 }
 
 impl<T: AudioDevice> APU<T> {
-    pub fn new(audio_device: T, volume_scale: i16) -> APU<T> {
+    /**
+     * `volume` is the fraction of the full sample range the loudest output takes.
+     */
+    pub fn new(audio_device: T, volume: f32) -> APU<T> {
+        let sample_rate = audio_device.sample_rate();
         APU {
             audio_device: audio_device,
-            volume_scale: volume_scale,
-            square1: Rc::new(RefCell::new(square::PulseGenerator::new())),
-            square2: Rc::new(RefCell::new(square::PulseGenerator::new())),
+            volume: volume,
+            square1: Rc::new(RefCell::new(square::PulseGenerator::new(
+                Negate::OnesComplement,
+            ))),
+            square2: Rc::new(RefCell::new(square::PulseGenerator::new(
+                Negate::TwosComplement,
+            ))),
+            triangle: Rc::new(RefCell::new(triangle::TriangleGenerator::new())),
+            noise: Rc::new(RefCell::new(noise::NoiseGenerator::new())),
+            dmc: Rc::new(RefCell::new(dmc::DeltaModulationChannel::new())),
+            frame_counter: Rc::new(RefCell::new(FrameCounter::new())),
+            clock_rate: Region::NTSC.cpu_clock_rate(),
+            output: Output::new(Region::NTSC.cpu_clock_rate(), sample_rate),
+            stems: None,
+            pacing_latency: None,
+            pacing_stalled: false,
             cpu_cycles: 0,
         }
     }
@@ -31,21 +174,242 @@ impl<T: AudioDevice> APU<T> {
     pub fn square2(&self) -> Rc<RefCell<square::PulseGenerator>> {
         self.square2.clone()
     }
+    pub fn triangle(&self) -> Rc<RefCell<triangle::TriangleGenerator>> {
+        self.triangle.clone()
+    }
+    pub fn noise(&self) -> Rc<RefCell<noise::NoiseGenerator>> {
+        self.noise.clone()
+    }
+    pub fn dmc(&self) -> Rc<RefCell<dmc::DeltaModulationChannel>> {
+        self.dmc.clone()
+    }
+    pub fn frame_counter(&self) -> Rc<RefCell<FrameCounter>> {
+        self.frame_counter.clone()
+    }
+    pub fn audio_device(&self) -> &T {
+        &self.audio_device
+    }
+
+    /**
+     * State of the IRQ line driven by the APU.
+     */
+    pub fn irq(&self) -> bool {
+        self.dmc.borrow().irq() || self.frame_counter.borrow().irq()
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.noise.borrow_mut().set_region(region);
+        self.dmc.borrow_mut().set_region(region);
+        self.frame_counter.borrow_mut().set_region(region);
+        self.end_audio_frame();
+        self.clock_rate = region.cpu_clock_rate();
+        self.output.blip.set_clock_rate(self.clock_rate);
+        for stem in self.stems.iter_mut().flat_map(|stems| stems.iter_mut()) {
+            stem.blip.set_clock_rate(self.clock_rate);
+        }
+    }
+
+    /**
+     * Lets the audio device set the speed of the emulation: `update` waits
+     * while more than `latency_millis` of audio is queued, and resamples a bit
+     * faster or slower to keep the queue at that level. Returns false, leaving
+     * the pacing off, if the device does not report its queue.
+     */
+    pub fn set_audio_pacing(&mut self, latency_millis: Option<u32>) -> bool {
+        match latency_millis {
+            Some(millis) => {
+                if self.audio_device.buffered_samples().is_none() {
+                    return false;
+                }
+                let latency = self.audio_device.sample_rate() as u64 * millis as u64 / 1000;
+                self.pacing_latency = Some(latency as usize);
+            }
+            None => {
+                self.pacing_latency = None;
+                self.set_rate_adjustment(1.0);
+            }
+        }
+        self.pacing_stalled = false;
+        true
+    }
+
+    /**
+     * True while the audio device sets the speed of the emulation, false when
+     * the pacing is off or the queue of the device stopped draining.
+     */
+    pub fn is_audio_paced(&self) -> bool {
+        self.pacing_latency.is_some() && !self.pacing_stalled
+    }
+
+    pub fn reset(&mut self) {
+        self.square1.borrow_mut().silence();
+        self.square2.borrow_mut().silence();
+        self.triangle.borrow_mut().silence();
+        self.noise.borrow_mut().silence();
+        self.dmc.borrow_mut().silence();
+        self.frame_counter.borrow_mut().reset();
+    }
 }
 
 impl<T: AudioDevice> APU<T> {
     pub fn update(&mut self, cpu_cycles: u8) {
+        self.clock_frame_counter(cpu_cycles);
         self.square1.borrow_mut().update(cpu_cycles);
         self.square2.borrow_mut().update(cpu_cycles);
+        self.triangle.borrow_mut().update(cpu_cycles);
+        self.noise.borrow_mut().update(cpu_cycles);
+        self.dmc.borrow_mut().update(cpu_cycles);
         self.cpu_cycles += cpu_cycles as u32;
-        if self.cpu_cycles >= 37 {
-            self.cpu_cycles -= 37;
-            self.audio_device
-                .play(&[(self.square1.borrow().pulse_value()
-                    + self.square2.borrow().pulse_value())
-                    * self.volume_scale]);
+
+        let output = self.mix();
+        self.output.set_level(self.cpu_cycles, output);
+        if self.stems.is_some() {
+            self.update_stems();
+        }
+        if self.cpu_cycles >= AUDIO_FRAME_CYCLES {
+            self.end_audio_frame();
+        }
+    }
+
+    /**
+     * Sends the samples up to now through the output filters to the audio device.
+     */
+    fn end_audio_frame(&mut self) {
+        let scale = self.volume * i16::max_value() as f32;
+        let cpu_cycles = self.cpu_cycles;
+        self.cpu_cycles = 0;
+        let samples = self.output.end_frame(cpu_cycles, scale);
+        if !samples.is_empty() {
+            self.audio_device.play(&samples);
+        }
+        if let Some(stems) = self.stems.as_mut() {
+            let stem_samples: Vec<Vec<i16>> = stems
+                .iter_mut()
+                .map(|stem| stem.end_frame(cpu_cycles, scale))
+                .collect();
+            self.audio_device.play_stems(&stem_samples);
+        }
+        self.start_or_stop_stems();
+        if let Some(latency) = self.pacing_latency {
+            self.pace(latency);
+        }
+    }
+
+    fn start_or_stop_stems(&mut self) {
+        if self.audio_device.wants_stems() == self.stems.is_some() {
+            return;
+        }
+        self.stems = if self.stems.is_none() {
+            let sample_rate = self.audio_device.sample_rate();
+            let rate_adjustment = self.output.blip.rate_adjustment();
+            //Starting from the current levels, so the stems do not start with a click
+            Some(
+                self.stem_levels()
+                    .iter()
+                    .map(|&level| {
+                        let mut stem = Output::new(self.clock_rate, sample_rate);
+                        stem.blip.set_rate_adjustment(rate_adjustment);
+                        stem.level = level;
+                        stem
+                    })
+                    .collect(),
+            )
+        } else {
+            None
+        };
+    }
+
+    fn update_stems(&mut self) {
+        let levels = self.stem_levels();
+        let cpu_cycles = self.cpu_cycles;
+        for (stem, &level) in self.stems.as_mut().unwrap().iter_mut().zip(levels.iter()) {
+            stem.set_level(cpu_cycles, level);
         }
     }
+
+    fn stem_levels(&self) -> [f32; 5] {
+        [
+            mixer::mix(self.square1.borrow().pulse_value(), 0, 0, 0, 0),
+            mixer::mix(0, self.square2.borrow().pulse_value(), 0, 0, 0),
+            mixer::mix(0, 0, self.triangle.borrow().triangle_value(), 0, 0),
+            mixer::mix(0, 0, 0, self.noise.borrow().noise_value(), 0),
+            mixer::mix(0, 0, 0, 0, self.dmc.borrow().dmc_value()),
+        ]
+    }
+
+    fn set_rate_adjustment(&mut self, rate_adjustment: f64) {
+        self.output.blip.set_rate_adjustment(rate_adjustment);
+        for stem in self.stems.iter_mut().flat_map(|stems| stems.iter_mut()) {
+            stem.blip.set_rate_adjustment(rate_adjustment);
+        }
+    }
+
+    fn pace(&mut self, latency: usize) {
+        let mut buffered = self.audio_device.buffered_samples().unwrap_or(0);
+        let shortfall = (latency as f64 - buffered as f64) / latency as f64;
+        let adjustment = shortfall.clamp(-1.0, 1.0) * MAX_RATE_ADJUSTMENT;
+        self.set_rate_adjustment(1.0 + adjustment);
+        if self.pacing_stalled {
+            self.pacing_stalled = buffered > latency;
+            return;
+        }
+        let start = Instant::now();
+        while buffered > latency {
+            if start.elapsed() >= Duration::from_millis(MAX_PACING_WAIT_MILLIS) {
+                self.pacing_stalled = true;
+                return;
+            }
+            sleep(Duration::from_millis(1));
+            buffered = self.audio_device.buffered_samples().unwrap_or(0);
+        }
+    }
+
+    /**
+     * Clocks only the parts of the APU that can raise an IRQ and the counters
+     * driven by the frame counter, for when the sound is turned off.
+     */
+    pub fn update_irq_sources(&mut self, cpu_cycles: u8) {
+        self.clock_frame_counter(cpu_cycles);
+        self.dmc.borrow_mut().update(cpu_cycles);
+    }
+
+    fn clock_frame_counter(&mut self, cpu_cycles: u8) {
+        for _ in 0..cpu_cycles {
+            let clock = self.frame_counter.borrow_mut().clock();
+            match clock {
+                Some(FrameClock::QuarterFrame) => self.quarter_frame(),
+                Some(FrameClock::HalfFrame) => {
+                    self.quarter_frame();
+                    self.half_frame();
+                }
+                None => (),
+            }
+        }
+    }
+
+    fn quarter_frame(&mut self) {
+        self.square1.borrow_mut().quarter_frame();
+        self.square2.borrow_mut().quarter_frame();
+        self.triangle.borrow_mut().quarter_frame();
+        self.noise.borrow_mut().quarter_frame();
+    }
+
+    fn half_frame(&mut self) {
+        self.square1.borrow_mut().half_frame();
+        self.square2.borrow_mut().half_frame();
+        self.triangle.borrow_mut().half_frame();
+        self.noise.borrow_mut().half_frame();
+    }
+
+    fn mix(&self) -> f32 {
+        mixer::mix(
+            self.square1.borrow().pulse_value(),
+            self.square2.borrow().pulse_value(),
+            self.triangle.borrow().triangle_value(),
+            self.noise.borrow().noise_value(),
+            self.dmc.borrow().dmc_value(),
+        )
+    }
 }
 
 impl AudioDevice for Rc<RefCell<Vec<i16>>> {
@@ -58,20 +422,229 @@ impl AudioDevice for Rc<RefCell<Vec<i16>>> {
 
 #[cfg(test)]
 mod test {
-    use super::APU;
-    use sound::counter::ClockTester;
-    use std::cell::RefCell;
+    use super::{AudioDevice, APU};
+    use region::Region;
+    use std::cell::{Cell, RefCell};
     use std::rc::Rc;
-    const FOUR_PULSE_SAMPLES_IN_CPU_CYCLES: u32 = 149;
+
+    /**
+     * Queue that plays `played_per_query` samples every time it is asked how
+     * many are left.
+     */
+    struct FakeQueue {
+        samples_played: Cell<usize>,
+        buffered: Cell<usize>,
+        played_per_query: usize,
+    }
+
+    impl FakeQueue {
+        fn new(buffered: usize, played_per_query: usize) -> Rc<FakeQueue> {
+            Rc::new(FakeQueue {
+                samples_played: Cell::new(0),
+                buffered: Cell::new(buffered),
+                played_per_query: played_per_query,
+            })
+        }
+    }
+
+    impl AudioDevice for Rc<FakeQueue> {
+        fn play(&self, samples: &[i16]) {
+            self.samples_played
+                .set(self.samples_played.get() + samples.len());
+            self.buffered.set(self.buffered.get() + samples.len());
+        }
+
+        fn buffered_samples(&self) -> Option<usize> {
+            let buffered = self.buffered.get().saturating_sub(self.played_per_query);
+            self.buffered.set(buffered);
+            Some(buffered)
+        }
+    }
+
+    /**
+     * Keeps the samples of each channel.
+     */
+    struct FakeStemRecorder {
+        samples: RefCell<usize>,
+        stems: RefCell<Vec<Vec<i16>>>,
+    }
+
+    impl AudioDevice for Rc<FakeStemRecorder> {
+        fn play(&self, samples: &[i16]) {
+            *self.samples.borrow_mut() += samples.len();
+        }
+
+        fn wants_stems(&self) -> bool {
+            true
+        }
+
+        fn play_stems(&self, stems: &[Vec<i16>]) {
+            let mut recorded = self.stems.borrow_mut();
+            recorded.resize(stems.len(), vec![]);
+            for (recorded, samples) in recorded.iter_mut().zip(stems.iter()) {
+                recorded.extend_from_slice(samples);
+            }
+        }
+    }
+
+    fn run_for_one_second<A: AudioDevice>(apu: &mut APU<A>) {
+        for _ in 0..Region::NTSC.cpu_clock_rate() / 7 {
+            apu.update(7);
+        }
+    }
 
     #[test]
     fn should_update_audio_device_at_correct_sample_rate() {
         let audio_device = Rc::new(RefCell::new(Vec::new()));
-        let apu = APU::new(audio_device.clone(), 1);
+        let mut apu = APU::new(audio_device.clone(), 1.0);
+
+        let mut cycles = 0u64;
+        while cycles < Region::NTSC.cpu_clock_rate() as u64 * 2 {
+            apu.update(7);
+            cycles += 7;
+        }
+        let played_cycles = cycles - apu.cpu_cycles as u64;
+        let expected = played_cycles * 48_000 / Region::NTSC.cpu_clock_rate() as u64;
+        let samples = audio_device.borrow().len() as u64;
+        assert!(
+            samples == expected || samples + 1 == expected,
+            "{}",
+            samples
+        );
+    }
+
+    #[test]
+    fn square_wave_should_be_centered_around_0() {
+        let audio_device = Rc::new(RefCell::new(Vec::new()));
+        let mut apu = APU::new(audio_device.clone(), 1.0);
+        {
+            let mut square = apu.square1.borrow_mut();
+            square.set_enabled(true);
+            square.duty(2);
+            square.halt_length(true);
+            square.volume(15);
+            square.timer(0xFE);
+            square.length(1);
+        }
+        run_for_one_second(&mut apu);
 
-        let mut clock = ClockTester::new(apu, FOUR_PULSE_SAMPLES_IN_CPU_CYCLES);
-        clock.count_down(|apu, tick| apu.update(tick), &|_, _| (), &|_, _| {
-            assert_eq!(audio_device.borrow().len(), 4)
+        let samples = audio_device.borrow();
+        let last_cycle = &samples[samples.len() - 480..];
+        let peak = last_cycle.iter().map(|&sample| sample.abs()).max().unwrap();
+        let sum: i64 = last_cycle.iter().map(|&sample| sample as i64).sum();
+        assert!(peak > 2000, "Peak {}", peak);
+        assert!((sum / 480).abs() < peak as i64 / 20, "Mean {}", sum / 480);
+    }
+
+    #[test]
+    fn loud_output_should_be_clamped_to_the_sample_range() {
+        let audio_device = Rc::new(RefCell::new(Vec::new()));
+        let mut apu = APU::new(audio_device.clone(), 4.0);
+        for square in [apu.square1(), apu.square2()].iter() {
+            let mut square = square.borrow_mut();
+            square.set_enabled(true);
+            square.duty(2);
+            square.halt_length(true);
+            square.volume(15);
+            square.timer(0xFE);
+            square.length(1);
+        }
+        {
+            let mut noise = apu.noise.borrow_mut();
+            noise.set_enabled(true);
+            noise.halt_length(true);
+            noise.volume(15);
+            noise.length(1);
+        }
+        apu.dmc.borrow_mut().direct_load(0x7F);
+        run_for_one_second(&mut apu);
+
+        let samples = audio_device.borrow();
+        assert_eq!(i16::max_value(), *samples.iter().max().unwrap());
+        assert_eq!(i16::min_value(), *samples.iter().min().unwrap());
+    }
+
+    #[test]
+    fn stems_should_have_each_channel_on_its_own() {
+        let recorder = Rc::new(FakeStemRecorder {
+            samples: RefCell::new(0),
+            stems: RefCell::new(vec![]),
         });
+        let mut apu = APU::new(recorder.clone(), 1.0);
+        {
+            let mut square = apu.square1.borrow_mut();
+            square.set_enabled(true);
+            square.duty(2);
+            square.halt_length(true);
+            square.volume(15);
+            square.timer(0xFE);
+            square.length(1);
+        }
+        run_for_one_second(&mut apu);
+
+        let stems = recorder.stems.borrow();
+        assert_eq!(5, stems.len());
+        //The stems start with the second audio frame
+        let samples = *recorder.samples.borrow();
+        assert!(samples - stems[0].len() <= 56, "{} samples", stems[0].len());
+        for stem in stems.iter() {
+            assert_eq!(stems[0].len(), stem.len());
+        }
+        let peak = |stem: &Vec<i16>| stem.iter().map(|&sample| sample.abs()).max().unwrap();
+        assert!(peak(&stems[0]) > 2000, "Peak {}", peak(&stems[0]));
+        for stem in stems[1..].iter() {
+            assert_eq!(0, peak(stem));
+        }
+    }
+
+    #[test]
+    fn audio_pacing_should_need_a_device_reporting_its_queue() {
+        let mut apu = APU::new(Rc::new(RefCell::new(Vec::new())), 1.0);
+        assert!(!apu.set_audio_pacing(Some(40)));
+        assert_eq!(None, apu.pacing_latency);
+
+        let mut apu = APU::new(FakeQueue::new(0, 0), 1.0);
+        assert!(apu.set_audio_pacing(Some(40)));
+        assert_eq!(Some(1920), apu.pacing_latency);
+    }
+
+    #[test]
+    fn short_queue_should_be_filled_with_more_samples() {
+        let queue = FakeQueue::new(0, 100);
+        let mut apu = APU::new(queue.clone(), 1.0);
+        apu.set_audio_pacing(Some(40));
+        run_for_one_second(&mut apu);
+
+        let played = queue.samples_played.get();
+        assert!(played > 48_100 && played < 48_300, "{} samples", played);
+    }
+
+    #[test]
+    fn full_queue_should_hold_the_emulation_until_it_drains() {
+        let queue = FakeQueue::new(10_000, 1000);
+        let mut apu = APU::new(queue.clone(), 1.0);
+        apu.set_audio_pacing(Some(40));
+        for _ in 0..(2048 / 8) {
+            apu.update(8);
+        }
+        assert!(queue.buffered.get() <= 1920, "{}", queue.buffered.get());
+    }
+
+    #[test]
+    fn queue_that_does_not_drain_should_hand_the_pacing_back_until_it_does() {
+        let queue = FakeQueue::new(10_000, 0);
+        let mut apu = APU::new(queue.clone(), 1.0);
+        apu.set_audio_pacing(Some(40));
+        assert!(apu.is_audio_paced());
+        for _ in 0..(2048 / 8) {
+            apu.update(8);
+        }
+        assert!(!apu.is_audio_paced());
+
+        queue.buffered.set(0);
+        for _ in 0..(2048 / 8) {
+            apu.update(8);
+        }
+        assert!(apu.is_audio_paced());
     }
 }
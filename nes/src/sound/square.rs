@@ -1,5 +1,13 @@
 use sound::envelope::Envelope;
 use sound::length_counter::LengthCounter;
+use sound::sweep::{Negate, Sweep};
+
+const DUTY_CYCLES: [[i16; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
 
 struct CircularBuffer {
     buffer: [i16; 8],
@@ -21,22 +29,30 @@ impl CircularBuffer {
 
 pub struct PulseGenerator {
     envelope: Envelope,
+    sweep: Sweep,
+    period: u16,
     timer_set: u32,
     timer: u32,
     sequencer: CircularBuffer,
     length: LengthCounter,
+    length_halted: bool,
+    enabled: bool,
 }
 
 impl PulseGenerator {
-    pub fn new() -> PulseGenerator {
+    pub fn new(negate_mode: Negate) -> PulseGenerator {
         PulseGenerator {
             envelope: Envelope::constant(0),
+            sweep: Sweep::new(negate_mode),
+            period: 0,
             timer_set: 0,
             timer: 0,
-            length: LengthCounter::new(0),
+            length: LengthCounter::default(),
+            length_halted: false,
+            enabled: false,
 
             sequencer: CircularBuffer {
-                buffer: [0, 1, 1, 1, 1, 0, 0, 0],
+                buffer: DUTY_CYCLES[0],
                 index: 0,
             },
         }
@@ -50,13 +66,28 @@ impl PulseGenerator {
         self.envelope = Envelope::decaying(volume);
     }
 
+    pub fn duty(&mut self, duty: u8) {
+        self.sequencer.buffer = DUTY_CYCLES[(duty & 0x03) as usize];
+    }
+
+    pub fn halt_length(&mut self, halted: bool) {
+        self.length_halted = halted;
+        self.length.halt(halted);
+    }
+
+    pub fn sweep(&mut self, value: u8) {
+        self.sweep.set(value);
+    }
+
     pub fn timer_low(&mut self, timer_low: u8) {
-        self.timer_set = (self.timer_set & 0xFF_FF_FE_00) | ((timer_low as u32) << 1);
+        self.period = (self.period & 0x700) | timer_low as u16;
+        self.timer_set = (self.period as u32) << 1;
         self.timer = 0;
     }
 
     pub fn timer_high(&mut self, timer_high: u8) {
-        self.timer_set = (self.timer_set & 0xFF_FF_F1_FF) | ((timer_high as u32) << 9);
+        self.period = (self.period & 0xFF) | ((timer_high as u16 & 0x07) << 8);
+        self.timer_set = (self.period as u32) << 1;
         self.timer = 0;
     }
 
@@ -66,13 +97,44 @@ impl PulseGenerator {
     }
 
     pub fn length(&mut self, length: u8) {
-        self.length = LengthCounter::new(length);
+        if self.enabled {
+            self.length = LengthCounter::new(length);
+            self.length.halt(self.length_halted);
+        }
+    }
+
+    /**
+     * Disabling the channel clears its length counter, which then stays at 0
+     * until the channel is enabled again.
+     */
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.length.clear();
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.length.value() > 0
+    }
+
+    pub fn silence(&mut self) {
+        self.set_enabled(false);
+        self.envelope = Envelope::constant(0);
+    }
+
+    pub fn quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn half_frame(&mut self) {
+        self.length.clock();
+        self.period = self.sweep.clock(self.period);
+        self.timer_set = (self.period as u32) << 1;
     }
 
     pub fn update(&mut self, cpu_cycles: u8) {
         self.timer += cpu_cycles as u32;
-        self.length.clock(cpu_cycles);
-        self.envelope.clock(cpu_cycles);
         if self.timer >= self.timer_set {
             self.timer -= self.timer_set;
             self.sequencer.next();
@@ -80,7 +142,7 @@ impl PulseGenerator {
     }
 
     pub fn pulse_value(&self) -> i16 {
-        if self.length.value() > 0 {
+        if self.length.value() > 0 && !self.sweep.mutes(self.period) {
             self.sequencer.get() * self.envelope.value() as i16
         } else {
             0
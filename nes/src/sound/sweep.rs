@@ -0,0 +1,147 @@
+/**
+ * How the sweep unit negates the period change, the only difference between
+ * the two pulse channels.
+ */
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Negate {
+    /**
+     * Pulse 1 subtracts one more than the change.
+     */
+    OnesComplement,
+    TwosComplement,
+}
+
+pub struct Sweep {
+    negate_mode: Negate,
+    enabled: bool,
+    divider_period: u8,
+    negate: bool,
+    shift: u8,
+    divider: u8,
+    reload: bool,
+}
+
+impl Sweep {
+    pub fn new(negate_mode: Negate) -> Sweep {
+        Sweep {
+            negate_mode: negate_mode,
+            enabled: false,
+            divider_period: 0,
+            negate: false,
+            shift: 0,
+            divider: 0,
+            reload: false,
+        }
+    }
+
+    /**
+     * Sets up the sweep from a write to $4001/$4005, the divider is reloaded
+     * on the next clock.
+     */
+    pub fn set(&mut self, value: u8) {
+        self.enabled = value & 0x80 != 0;
+        self.divider_period = (value >> 4) & 0x07;
+        self.negate = value & 0x08 != 0;
+        self.shift = value & 0x07;
+        self.reload = true;
+    }
+
+    pub fn target_period(&self, period: u16) -> u16 {
+        let change = period >> self.shift;
+        if !self.negate {
+            period + change
+        } else {
+            match self.negate_mode {
+                Negate::OnesComplement => period.saturating_sub(change + 1),
+                Negate::TwosComplement => period.saturating_sub(change),
+            }
+        }
+    }
+
+    /**
+     * The channel is muted when its period is too short or the sweep would
+     * take it out of range, whether the sweep is enabled or not.
+     */
+    pub fn mutes(&self, period: u16) -> bool {
+        period < 8 || self.target_period(period) > 0x7FF
+    }
+
+    /**
+     * Clocked by the frame counter every half frame, returns the new period.
+     */
+    pub fn clock(&mut self, period: u16) -> u16 {
+        let mut new_period = period;
+        if self.divider == 0 && self.enabled && self.shift > 0 && !self.mutes(period) {
+            new_period = self.target_period(period);
+        }
+        if self.divider == 0 || self.reload {
+            self.divider = self.divider_period;
+            self.reload = false;
+        } else {
+            self.divider -= 1;
+        }
+        new_period
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Negate, Sweep};
+
+    fn sweep(negate_mode: Negate, value: u8) -> Sweep {
+        let mut sweep = Sweep::new(negate_mode);
+        sweep.set(value);
+        sweep
+    }
+
+    #[test]
+    fn pulse_1_should_negate_with_ones_complement() {
+        assert_eq!(
+            0x100 - 0x40 - 1,
+            sweep(Negate::OnesComplement, 0x8A).target_period(0x100)
+        );
+        assert_eq!(
+            0x100 - 0x40,
+            sweep(Negate::TwosComplement, 0x8A).target_period(0x100)
+        );
+        assert_eq!(
+            0x100 + 0x40,
+            sweep(Negate::OnesComplement, 0x82).target_period(0x100)
+        );
+    }
+
+    #[test]
+    fn periods_out_of_range_should_mute_even_when_disabled() {
+        let adding = sweep(Negate::TwosComplement, 0x00);
+        assert!(adding.mutes(7));
+        assert!(!adding.mutes(8));
+        assert!(!adding.mutes(0x3FF));
+        assert!(adding.mutes(0x400));
+
+        let negating = sweep(Negate::TwosComplement, 0x08);
+        assert!(!negating.mutes(0x7FF));
+    }
+
+    #[test]
+    fn period_should_change_every_divider_period_plus_one_clocks() {
+        let mut sweep = sweep(Negate::TwosComplement, 0xA1);
+        let mut period = 0x100;
+        let mut periods = vec![];
+        for _ in 0..7 {
+            period = sweep.clock(period);
+            periods.push(period);
+        }
+        assert_eq!(
+            vec![0x180, 0x180, 0x180, 0x240, 0x240, 0x240, 0x360],
+            periods
+        );
+    }
+
+    #[test]
+    fn sweep_should_not_change_the_period_without_a_shift() {
+        let mut sweep = sweep(Negate::TwosComplement, 0x80);
+        for _ in 0..10 {
+            assert_eq!(0x100, sweep.clock(0x100));
+        }
+    }
+}
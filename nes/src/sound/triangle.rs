@@ -0,0 +1,187 @@
+use sound::length_counter::LengthCounter;
+
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+    13, 14, 15,
+];
+
+pub struct TriangleGenerator {
+    timer_set: u32,
+    timer: u32,
+    sequence_index: usize,
+    length: LengthCounter,
+    enabled: bool,
+    control: bool,
+    linear_counter_set: u8,
+    linear_counter: u8,
+    linear_counter_reload: bool,
+}
+
+impl TriangleGenerator {
+    pub fn new() -> TriangleGenerator {
+        TriangleGenerator {
+            timer_set: 0,
+            timer: 0,
+            sequence_index: 0,
+            length: LengthCounter::default(),
+            enabled: false,
+            control: false,
+            linear_counter_set: 0,
+            linear_counter: 0,
+            linear_counter_reload: false,
+        }
+    }
+
+    /**
+     * The control flag both halts the length counter and keeps the linear
+     * counter reloading.
+     */
+    pub fn linear_counter(&mut self, control: bool, value: u8) {
+        self.control = control;
+        self.linear_counter_set = value & 0x7F;
+        self.length.halt(control);
+    }
+
+    pub fn timer_low(&mut self, timer_low: u8) {
+        self.timer_set = (self.timer_set & 0x700) | timer_low as u32;
+    }
+
+    pub fn timer_high(&mut self, timer_high: u8) {
+        self.timer_set = (self.timer_set & 0xFF) | ((timer_high as u32 & 0x07) << 8);
+    }
+
+    pub fn length(&mut self, length: u8) {
+        if self.enabled {
+            self.length = LengthCounter::new(length);
+            self.length.halt(self.control);
+        }
+        self.linear_counter_reload = true;
+    }
+
+    /**
+     * Disabling the channel clears its length counter, which then stays at 0
+     * until the channel is enabled again.
+     */
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.length.clear();
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.length.value() > 0
+    }
+
+    pub fn silence(&mut self) {
+        self.set_enabled(false);
+        self.linear_counter = 0;
+    }
+
+    /**
+     * Clocks the linear counter.
+     */
+    pub fn quarter_frame(&mut self) {
+        if self.linear_counter_reload {
+            self.linear_counter = self.linear_counter_set;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+        if !self.control {
+            self.linear_counter_reload = false;
+        }
+    }
+
+    pub fn half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    pub fn update(&mut self, cpu_cycles: u8) {
+        //Periods below 2 are ultrasonic, games use them to mute the channel
+        let active = self.linear_counter > 0 && self.length.value() > 0 && self.timer_set >= 2;
+        self.timer += cpu_cycles as u32;
+        while self.timer > self.timer_set {
+            self.timer -= self.timer_set + 1;
+            if active {
+                self.sequence_index = (self.sequence_index + 1) % SEQUENCE.len();
+            }
+        }
+    }
+
+    /**
+     * The sequencer stops rather than going silent, so the last value is held.
+     */
+    pub fn triangle_value(&self) -> i16 {
+        SEQUENCE[self.sequence_index] as i16
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TriangleGenerator;
+
+    fn started(period: u32, linear_counter: u8) -> TriangleGenerator {
+        let mut triangle = TriangleGenerator::new();
+        triangle.set_enabled(true);
+        triangle.linear_counter(false, linear_counter);
+        triangle.timer_low(period as u8);
+        triangle.timer_high((period >> 8) as u8);
+        triangle.length(1);
+        triangle.quarter_frame();
+        triangle
+    }
+
+    #[test]
+    fn triangle_should_step_through_the_sequence_every_timer_period() {
+        let mut triangle = started(9, 0x7F);
+        let start = triangle.sequence_index;
+        let mut values = vec![];
+        for _ in 0..32 {
+            values.push(triangle.triangle_value());
+            for _ in 0..10 {
+                triangle.update(1);
+            }
+        }
+        assert_eq!((start + 32) % 32, triangle.sequence_index);
+        values.sort();
+        let expected: Vec<i16> = (0..16).flat_map(|v| vec![v, v]).collect();
+        assert_eq!(expected, values);
+    }
+
+    #[test]
+    fn triangle_should_hold_its_value_when_the_linear_counter_runs_out() {
+        let mut triangle = started(9, 2);
+        triangle.quarter_frame();
+        triangle.quarter_frame();
+        let value = triangle.triangle_value();
+        for _ in 0..1000 {
+            triangle.update(1);
+            assert_eq!(value, triangle.triangle_value());
+        }
+    }
+
+    #[test]
+    fn control_flag_should_keep_reloading_the_linear_counter() {
+        let mut triangle = started(9, 1);
+        triangle.linear_counter(true, 1);
+        triangle.length(1);
+        for _ in 0..4 {
+            triangle.quarter_frame();
+        }
+        let value = triangle.triangle_value();
+        for _ in 0..10 {
+            triangle.update(1);
+        }
+        assert!(value != triangle.triangle_value());
+    }
+
+    #[test]
+    fn ultrasonic_periods_should_not_step_the_sequencer() {
+        let mut triangle = started(1, 0x7F);
+        let value = triangle.triangle_value();
+        for _ in 0..100 {
+            triangle.update(7);
+            assert_eq!(value, triangle.triangle_value());
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+
+const HEADER_SIZE: u32 = 44;
+
+/**
+ * Writes mono 16-bit PCM samples to a WAV file. The sizes in the header are
+ * filled in by `finish`, or when the writer is dropped.
+ */
+pub struct WavWriter<W: Write + Seek> {
+    output: Option<W>,
+    data_size: u32,
+}
+
+impl WavWriter<BufWriter<File>> {
+    pub fn create(path: &str, sample_rate: u32) -> io::Result<WavWriter<BufWriter<File>>> {
+        WavWriter::new(BufWriter::new(File::create(path)?), sample_rate)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut output: W, sample_rate: u32) -> io::Result<WavWriter<W>> {
+        output.write_all(b"RIFF")?;
+        write_u32(&mut output, HEADER_SIZE - 8)?;
+        output.write_all(b"WAVE")?;
+
+        output.write_all(b"fmt ")?;
+        write_u32(&mut output, 16)?;
+        write_u16(&mut output, 1)?; //PCM
+        write_u16(&mut output, 1)?; //Mono
+        write_u32(&mut output, sample_rate)?;
+        write_u32(&mut output, sample_rate * 2)?; //Bytes per second
+        write_u16(&mut output, 2)?; //Bytes per sample
+        write_u16(&mut output, 16)?; //Bits per sample
+
+        output.write_all(b"data")?;
+        write_u32(&mut output, 0)?;
+        Ok(WavWriter {
+            output: Some(output),
+            data_size: 0,
+        })
+    }
+
+    /**
+     * Fails without writing anything once the file would grow past the 4 GiB
+     * the header can describe.
+     */
+    pub fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+        let data_size = self.data_size as u64 + samples.len() as u64 * 2;
+        if (HEADER_SIZE - 8) as u64 + data_size > u32::max_value() as u64 {
+            return Err(io::Error::other("WAV files cannot be larger than 4 GiB"));
+        }
+        let output = self.output.as_mut().unwrap();
+        for &sample in samples.iter() {
+            write_u16(output, sample as u16)?;
+        }
+        self.data_size = data_size as u32;
+        Ok(())
+    }
+
+    /**
+     * Fills in the sizes and returns the output.
+     */
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_sizes()?;
+        Ok(self.output.take().unwrap())
+    }
+
+    fn write_sizes(&mut self) -> io::Result<()> {
+        let data_size = self.data_size;
+        let output = self.output.as_mut().unwrap();
+        output.seek(SeekFrom::Start(4))?;
+        write_u32(output, HEADER_SIZE - 8 + data_size)?;
+        output.seek(SeekFrom::Start(HEADER_SIZE as u64 - 4))?;
+        write_u32(output, data_size)?;
+        output.seek(SeekFrom::End(0))?;
+        output.flush()
+    }
+}
+
+impl<W: Write + Seek> Drop for WavWriter<W> {
+    fn drop(&mut self) {
+        if self.output.is_some() {
+            let _ = self.write_sizes();
+        }
+    }
+}
+
+fn write_u16<W: Write>(output: &mut W, value: u16) -> io::Result<()> {
+    output.write_all(&[value as u8, (value >> 8) as u8])
+}
+
+fn write_u32<W: Write>(output: &mut W, value: u32) -> io::Result<()> {
+    write_u16(output, value as u16)?;
+    write_u16(output, (value >> 16) as u16)
+}
+
+#[cfg(test)]
+mod test {
+    use super::WavWriter;
+    use std::io::Cursor;
+
+    #[test]
+    fn header_should_describe_mono_16_bit_pcm() {
+        let wav = WavWriter::new(Cursor::new(vec![]), 48_000)
+            .unwrap()
+            .finish()
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(44, wav.len());
+        assert_eq!(b"RIFF", &wav[0..4]);
+        assert_eq!(&[36, 0, 0, 0], &wav[4..8]);
+        assert_eq!(b"WAVEfmt ", &wav[8..16]);
+        assert_eq!(&[16, 0, 0, 0, 1, 0, 1, 0], &wav[16..24]);
+        assert_eq!(&[0x80, 0xBB, 0, 0], &wav[24..28]);
+        assert_eq!(&[0x00, 0x77, 0x01, 0], &wav[28..32]);
+        assert_eq!(&[2, 0, 16, 0], &wav[32..36]);
+        assert_eq!(b"data", &wav[36..40]);
+        assert_eq!(&[0, 0, 0, 0], &wav[40..44]);
+    }
+
+    #[test]
+    fn samples_should_be_little_endian_and_counted_in_the_header() {
+        let mut writer = WavWriter::new(Cursor::new(vec![]), 44_100).unwrap();
+        writer.write(&[1, -2]).unwrap();
+        writer.write(&[0x1234]).unwrap();
+        let wav = writer.finish().unwrap().into_inner();
+
+        assert_eq!(&[36 + 6, 0, 0, 0], &wav[4..8]);
+        assert_eq!(&[6, 0, 0, 0], &wav[40..44]);
+        assert_eq!(&[0x01, 0x00, 0xFE, 0xFF, 0x34, 0x12], &wav[44..]);
+    }
+
+    #[test]
+    fn samples_past_the_4_gib_limit_should_be_an_error() {
+        let mut writer = WavWriter::new(Cursor::new(vec![]), 44_100).unwrap();
+        writer.data_size = u32::max_value() - 36 - 4;
+        writer.write(&[1, 2]).unwrap();
+        assert!(writer.write(&[3]).is_err());
+
+        let wav = writer.finish().unwrap().into_inner();
+        assert_eq!(&[0xFF, 0xFF, 0xFF, 0xFF], &wav[4..8]);
+        assert_eq!(&[0x01, 0x00, 0x02, 0x00], &wav[44..]);
+    }
+}
@@ -0,0 +1,416 @@
+use cpu::opcodes::*;
+use cpu::CPU;
+use memory::{Address, CPUMemory};
+use ppu::screen::Screen;
+use sound::AudioDevice;
+use NES;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Mode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    IndirectX,
+    IndirectY,
+    Relative,
+}
+
+use self::Mode::*;
+
+impl Mode {
+    fn operand_bytes(&self) -> u16 {
+        match *self {
+            Implied | Accumulator => 0,
+            Absolute | AbsoluteX | AbsoluteY | Indirect => 2,
+            _ => 1,
+        }
+    }
+}
+
+const OFFICIAL: bool = true;
+const UNOFFICIAL: bool = false;
+
+const INSTRUCTIONS: [(OpCode, &'static str, Mode, bool); 156] = [
+    (BRK, "BRK", Implied, OFFICIAL),
+    (ORA_INDIRECT_X, "ORA", IndirectX, OFFICIAL),
+    (ORA_ZERO_PAGE, "ORA", ZeroPage, OFFICIAL),
+    (ASL_ZERO_PAGE, "ASL", ZeroPage, OFFICIAL),
+    (PHP, "PHP", Implied, OFFICIAL),
+    (ORA_IMMEDIATE, "ORA", Immediate, OFFICIAL),
+    (ASL_ACCUMULATOR, "ASL", Accumulator, OFFICIAL),
+    (ORA_ABSOLUTE, "ORA", Absolute, OFFICIAL),
+    (ASL_ABSOLUTE, "ASL", Absolute, OFFICIAL),
+    (BRANCH_PLUS, "BPL", Relative, OFFICIAL),
+    (ORA_INDIRECT_Y, "ORA", IndirectY, OFFICIAL),
+    (IGN_INDIRECT_X_1, "NOP", ZeroPageX, UNOFFICIAL),
+    (ORA_ZERO_PAGE_X, "ORA", ZeroPageX, OFFICIAL),
+    (ASL_ZERO_PAGE_X, "ASL", ZeroPageX, OFFICIAL),
+    (CLC, "CLC", Implied, OFFICIAL),
+    (ORA_ABSOLUTE_Y, "ORA", AbsoluteY, OFFICIAL),
+    (ORA_ABSOLUTE_X, "ORA", AbsoluteX, OFFICIAL),
+    (ASL_ABSOLUTE_X, "ASL", AbsoluteX, OFFICIAL),
+    (JSR_ABSOLUTE, "JSR", Absolute, OFFICIAL),
+    (AND_INDIRECT_X, "AND", IndirectX, OFFICIAL),
+    (BIT_ZERO_PAGE, "BIT", ZeroPage, OFFICIAL),
+    (AND_ZERO_PAGE, "AND", ZeroPage, OFFICIAL),
+    (ROL_ZERO_PAGE, "ROL", ZeroPage, OFFICIAL),
+    (PLP, "PLP", Implied, OFFICIAL),
+    (AND_IMMEDIATE, "AND", Immediate, OFFICIAL),
+    (ROL_ACCUMULATOR, "ROL", Accumulator, OFFICIAL),
+    (BIT_ABSOLUTE, "BIT", Absolute, OFFICIAL),
+    (AND_ABSOLUTE, "AND", Absolute, OFFICIAL),
+    (ROL_ABSOLUTE, "ROL", Absolute, OFFICIAL),
+    (BRANCH_MINUS, "BMI", Relative, OFFICIAL),
+    (AND_INDIRECT_Y, "AND", IndirectY, OFFICIAL),
+    (AND_ZERO_PAGE_X, "AND", ZeroPageX, OFFICIAL),
+    (ROL_ZERO_PAGE_X, "ROL", ZeroPageX, OFFICIAL),
+    (SEC, "SEC", Implied, OFFICIAL),
+    (AND_ABSOLUTE_Y, "AND", AbsoluteY, OFFICIAL),
+    (AND_ABSOLUTE_X, "AND", AbsoluteX, OFFICIAL),
+    (ROL_ABSOLUTE_X, "ROL", AbsoluteX, OFFICIAL),
+    (RTI, "RTI", Implied, OFFICIAL),
+    (EOR_INDIRECT_X, "EOR", IndirectX, OFFICIAL),
+    (EOR_ZERO_PAGE, "EOR", ZeroPage, OFFICIAL),
+    (LSR_ZERO_PAGE, "LSR", ZeroPage, OFFICIAL),
+    (PHA, "PHA", Implied, OFFICIAL),
+    (EOR_IMMEDIATE, "EOR", Immediate, OFFICIAL),
+    (LSR_ACCUMULATOR, "LSR", Accumulator, OFFICIAL),
+    (JMP_ABSOLUTE, "JMP", Absolute, OFFICIAL),
+    (EOR_ABSOLUTE, "EOR", Absolute, OFFICIAL),
+    (LSR_ABSOLUTE, "LSR", Absolute, OFFICIAL),
+    (BRANCH_OVERFLOW_CLEAR, "BVC", Relative, OFFICIAL),
+    (EOR_INDIRECT_Y, "EOR", IndirectY, OFFICIAL),
+    (IGN_INDIRECT_X_3, "NOP", ZeroPageX, UNOFFICIAL),
+    (EOR_ZERO_PAGE_X, "EOR", ZeroPageX, OFFICIAL),
+    (LSR_ZERO_PAGE_X, "LSR", ZeroPageX, OFFICIAL),
+    (SRE_INDIRECT_X, "SRE", ZeroPageX, UNOFFICIAL),
+    (CLI, "CLI", Implied, OFFICIAL),
+    (EOR_ABSOLUTE_Y, "EOR", AbsoluteY, OFFICIAL),
+    (EOR_ABSOLUTE_X, "EOR", AbsoluteX, OFFICIAL),
+    (LSR_ABSOLUTE_X, "LSR", AbsoluteX, OFFICIAL),
+    (RTS, "RTS", Implied, OFFICIAL),
+    (ADC_INDIRECT_X, "ADC", IndirectX, OFFICIAL),
+    (ADC_ZERO_PAGE, "ADC", ZeroPage, OFFICIAL),
+    (ROR_ZERO_PAGE, "ROR", ZeroPage, OFFICIAL),
+    (PLA, "PLA", Implied, OFFICIAL),
+    (ADC_IMMEDIATE, "ADC", Immediate, OFFICIAL),
+    (ROR_ACCUMULATOR, "ROR", Accumulator, OFFICIAL),
+    (JMP_INDIRECT, "JMP", Indirect, OFFICIAL),
+    (ADC_ABSOLUTE, "ADC", Absolute, OFFICIAL),
+    (ROR_ABSOLUTE, "ROR", Absolute, OFFICIAL),
+    (BRANCH_OVERFLOW_SET, "BVS", Relative, OFFICIAL),
+    (ADC_INDIRECT_Y, "ADC", IndirectY, OFFICIAL),
+    (ADC_ZERO_PAGE_X, "ADC", ZeroPageX, OFFICIAL),
+    (ROR_ZERO_PAGE_X, "ROR", ZeroPageX, OFFICIAL),
+    (SEI, "SEI", Implied, OFFICIAL),
+    (ADC_ABSOLUTE_Y, "ADC", AbsoluteY, OFFICIAL),
+    (ADC_ABSOLUTE_X, "ADC", AbsoluteX, OFFICIAL),
+    (ROR_ABSOLUTE_X, "ROR", AbsoluteX, OFFICIAL),
+    (STA_INDIRECT_X, "STA", IndirectX, OFFICIAL),
+    (STY_ZERO_PAGE, "STY", ZeroPage, OFFICIAL),
+    (STA_ZERO_PAGE, "STA", ZeroPage, OFFICIAL),
+    (STX_ZERO_PAGE, "STX", ZeroPage, OFFICIAL),
+    (DEY, "DEY", Implied, OFFICIAL),
+    (TXA, "TXA", Implied, OFFICIAL),
+    (STY_ABSOLUTE, "STY", Absolute, OFFICIAL),
+    (STA_ABSOLUTE, "STA", Absolute, OFFICIAL),
+    (STX_ABSOLUTE, "STX", Absolute, OFFICIAL),
+    (BRANCH_CARRY_CLEAR, "BCC", Relative, OFFICIAL),
+    (STA_INDIRECT_Y, "STA", IndirectY, OFFICIAL),
+    (STY_ZERO_PAGE_X, "STY", ZeroPageX, OFFICIAL),
+    (STA_ZERO_PAGE_X, "STA", ZeroPageX, OFFICIAL),
+    (STX_ZERO_PAGE_Y, "STX", ZeroPageY, OFFICIAL),
+    (TYA, "TYA", Implied, OFFICIAL),
+    (STA_ABSOLUTE_Y, "STA", AbsoluteY, OFFICIAL),
+    (TXS, "TXS", Implied, OFFICIAL),
+    (STA_ABSOLUTE_X, "STA", AbsoluteX, OFFICIAL),
+    (LDY_IMMEDIATE, "LDY", Immediate, OFFICIAL),
+    (LDA_INDIRECT_X, "LDA", IndirectX, OFFICIAL),
+    (LDX_IMMEDIATE, "LDX", Immediate, OFFICIAL),
+    (LDY_ZERO_PAGE, "LDY", ZeroPage, OFFICIAL),
+    (LDA_ZERO_PAGE, "LDA", ZeroPage, OFFICIAL),
+    (LDX_ZERO_PAGE, "LDX", ZeroPage, OFFICIAL),
+    (TAY, "TAY", Implied, OFFICIAL),
+    (LDA_IMMEDIATE, "LDA", Immediate, OFFICIAL),
+    (TAX, "TAX", Implied, OFFICIAL),
+    (LDY_ABSOLUTE, "LDY", Absolute, OFFICIAL),
+    (LDA_ABSOLUTE, "LDA", Absolute, OFFICIAL),
+    (LDX_ABSOLUTE, "LDX", Absolute, OFFICIAL),
+    (BRANCH_CARRY_SET, "BCS", Relative, OFFICIAL),
+    (LDA_INDIRECT_Y, "LDA", IndirectY, OFFICIAL),
+    (LDY_ZERO_PAGE_X, "LDY", ZeroPageX, OFFICIAL),
+    (LDA_ZERO_PAGE_X, "LDA", ZeroPageX, OFFICIAL),
+    (LDX_ZERO_PAGE_Y, "LDX", ZeroPageY, OFFICIAL),
+    (CLV, "CLV", Implied, OFFICIAL),
+    (LDA_ABSOLUTE_Y, "LDA", AbsoluteY, OFFICIAL),
+    (TSX, "TSX", Implied, OFFICIAL),
+    (LDY_ABSOLUTE_X, "LDY", AbsoluteX, OFFICIAL),
+    (LDA_ABSOLUTE_X, "LDA", AbsoluteX, OFFICIAL),
+    (LDX_ABSOLUTE_Y, "LDX", AbsoluteY, OFFICIAL),
+    (CPY_IMMEDIATE, "CPY", Immediate, OFFICIAL),
+    (CMP_INDIRECT_X, "CMP", IndirectX, OFFICIAL),
+    (CPY_ZERO_PAGE, "CPY", ZeroPage, OFFICIAL),
+    (CMP_ZERO_PAGE, "CMP", ZeroPage, OFFICIAL),
+    (DEC_ZERO_PAGE, "DEC", ZeroPage, OFFICIAL),
+    (INY, "INY", Implied, OFFICIAL),
+    (CMP_IMMEDIATE, "CMP", Immediate, OFFICIAL),
+    (DEX, "DEX", Implied, OFFICIAL),
+    (CPY_ABSOLUTE, "CPY", Absolute, OFFICIAL),
+    (CMP_ABSOLUTE, "CMP", Absolute, OFFICIAL),
+    (DEC_ABSOLUTE, "DEC", Absolute, OFFICIAL),
+    (BRANCH_NOT_EQUAL, "BNE", Relative, OFFICIAL),
+    (CMP_INDIRECT_Y, "CMP", IndirectY, OFFICIAL),
+    (CMP_ZERO_PAGE_X, "CMP", ZeroPageX, OFFICIAL),
+    (DEC_ZERO_PAGE_X, "DEC", ZeroPageX, OFFICIAL),
+    (CLD, "CLD", Implied, OFFICIAL),
+    (CMP_ABSOLUTE_Y, "CMP", AbsoluteY, OFFICIAL),
+    (CMP_ABSOLUTE_X, "CMP", AbsoluteX, OFFICIAL),
+    (DEC_ABSOLUTE_X, "DEC", AbsoluteX, OFFICIAL),
+    (CPX_IMMEDIATE, "CPX", Immediate, OFFICIAL),
+    (SBC_INDIRECT_X, "SBC", IndirectX, OFFICIAL),
+    (ISC_INDIRECT_X, "ISB", IndirectX, UNOFFICIAL),
+    (CPX_ZERO_PAGE, "CPX", ZeroPage, OFFICIAL),
+    (SBC_ZERO_PAGE, "SBC", ZeroPage, OFFICIAL),
+    (INC_ZERO_PAGE, "INC", ZeroPage, OFFICIAL),
+    (INX, "INX", Implied, OFFICIAL),
+    (SBC_IMMEDIATE, "SBC", Immediate, OFFICIAL),
+    (NOP_IMPLIED, "NOP", Implied, OFFICIAL),
+    (CPX_ABSOLUTE, "CPX", Absolute, OFFICIAL),
+    (SBC_ABSOLUTE, "SBC", Absolute, OFFICIAL),
+    (INC_ABSOLUTE, "INC", Absolute, OFFICIAL),
+    (BRANCH_EQUAL, "BEQ", Relative, OFFICIAL),
+    (SBC_INDIRECT_Y, "SBC", IndirectY, OFFICIAL),
+    (SBC_ZERO_PAGE_X, "SBC", ZeroPageX, OFFICIAL),
+    (INC_ZERO_PAGE_X, "INC", ZeroPageX, OFFICIAL),
+    (SED, "SED", Implied, OFFICIAL),
+    (SBC_ABSOLUTE_Y, "SBC", AbsoluteY, OFFICIAL),
+    (SBC_ABSOLUTE_X, "SBC", AbsoluteX, OFFICIAL),
+    (INC_ABSOLUTE_X, "INC", AbsoluteX, OFFICIAL),
+    (ISC_ABSOLUTE_X, "ISB", AbsoluteX, UNOFFICIAL),
+];
+
+/**
+ * One line of a Nintendulator style trace for the instruction about to be
+ * executed, e.g.
+ * C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+ */
+pub fn trace<'a, T, A>(nes: &NES<'a, T, A>) -> String
+where
+    T: Screen + Sized,
+    A: AudioDevice + Sized,
+{
+    let ppu = nes.ppu.borrow();
+    format!(
+        "{} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+        disassemble(&nes.cpu, &nes.memory),
+        nes.cpu.accumulator(),
+        nes.cpu.register_x(),
+        nes.cpu.register_y(),
+        (nes.cpu.processor_status() | 0x20) & !0x10,
+        nes.cpu.stack_pointer,
+        ppu.scanline(),
+        ppu.dot(),
+        nes.cycle_count
+    )
+}
+
+/**
+ * The address, bytes and assembly of the instruction at the program counter,
+ * padded to 47 columns. Unofficial opcodes are marked with a '*'.
+ */
+pub fn disassemble(cpu: &CPU, memory: &CPUMemory) -> String {
+    let pc = cpu.program_counter();
+    let op_code = memory.peek(pc);
+    let (mnemonic, mode, official) = match INSTRUCTIONS.iter().find(|i| i.0 == op_code) {
+        Some(&(_, mnemonic, mode, official)) => (mnemonic, mode, official),
+        None => ("???", Implied, UNOFFICIAL),
+    };
+    let bytes: Vec<String> = (0..(mode.operand_bytes() + 1))
+        .map(|i| format!("{:02X}", memory.peek(pc.wrapping_add(i))))
+        .collect();
+    format!(
+        "{:04X}  {:<8} {}{} {:<27}",
+        pc,
+        bytes.join(" "),
+        if official { ' ' } else { '*' },
+        mnemonic,
+        operand(cpu, memory, mnemonic, mode)
+    )
+}
+
+fn operand(cpu: &CPU, memory: &CPUMemory, mnemonic: &str, mode: Mode) -> String {
+    let pc = cpu.program_counter();
+    let byte = memory.peek(pc.wrapping_add(1));
+    let word = read_word(memory, pc.wrapping_add(1));
+    match mode {
+        Implied => String::new(),
+        Accumulator => "A".to_string(),
+        Immediate => format!("#${:02X}", byte),
+        ZeroPage => format!("${:02X} = {:02X}", byte, memory.peek(byte as Address)),
+        ZeroPageX => {
+            let address = byte.wrapping_add(cpu.register_x());
+            format!(
+                "${:02X},X @ {:02X} = {:02X}",
+                byte,
+                address,
+                memory.peek(address as Address)
+            )
+        }
+        ZeroPageY => {
+            let address = byte.wrapping_add(cpu.register_y());
+            format!(
+                "${:02X},Y @ {:02X} = {:02X}",
+                byte,
+                address,
+                memory.peek(address as Address)
+            )
+        }
+        Absolute if mnemonic == "JMP" || mnemonic == "JSR" => format!("${:04X}", word),
+        Absolute => format!("${:04X} = {:02X}", word, memory.peek(word)),
+        AbsoluteX => {
+            let address = word.wrapping_add(cpu.register_x() as Address);
+            format!(
+                "${:04X},X @ {:04X} = {:02X}",
+                word,
+                address,
+                memory.peek(address)
+            )
+        }
+        AbsoluteY => {
+            let address = word.wrapping_add(cpu.register_y() as Address);
+            format!(
+                "${:04X},Y @ {:04X} = {:02X}",
+                word,
+                address,
+                memory.peek(address)
+            )
+        }
+        Indirect => {
+            //The indirect jump doesn't carry into the high byte of the pointer
+            let msb_address = (word & 0xFF00) | (word.wrapping_add(1) & 0x00FF);
+            let target = (memory.peek(msb_address) as Address) << 8 | memory.peek(word) as Address;
+            format!("(${:04X}) = {:04X}", word, target)
+        }
+        IndirectX => {
+            let pointer = byte.wrapping_add(cpu.register_x());
+            let address = read_zero_page_word(memory, pointer);
+            format!(
+                "(${:02X},X) @ {:02X} = {:04X} = {:02X}",
+                byte,
+                pointer,
+                address,
+                memory.peek(address)
+            )
+        }
+        IndirectY => {
+            let base_address = read_zero_page_word(memory, byte);
+            let address = base_address.wrapping_add(cpu.register_y() as Address);
+            format!(
+                "(${:02X}),Y = {:04X} @ {:04X} = {:02X}",
+                byte,
+                base_address,
+                address,
+                memory.peek(address)
+            )
+        }
+        Relative => format!(
+            "${:04X}",
+            pc.wrapping_add(2).wrapping_add(byte as i8 as Address)
+        ),
+    }
+}
+
+fn read_word(memory: &CPUMemory, address: Address) -> Address {
+    (memory.peek(address.wrapping_add(1)) as Address) << 8 | memory.peek(address) as Address
+}
+
+fn read_zero_page_word(memory: &CPUMemory, address: u8) -> Address {
+    (memory.peek(address.wrapping_add(1) as Address) as Address) << 8
+        | memory.peek(address as Address) as Address
+}
+
+#[cfg(test)]
+mod test {
+    use super::disassemble;
+    use cpu::CpuBuilder;
+    use memory::{CPUMemory, Memory};
+
+    fn cpu_memory() -> CPUMemory<'static> {
+        let cartridge = memory!(
+            0xC000 => 0x4C,
+            0xC001 => 0xF5,
+            0xC002 => 0xC5,
+
+            0xC5F7 => 0x86,
+            0xC5F8 => 0x00,
+
+            0xC72D => 0xEA,
+
+            0xD959 => 0xB1,
+            0xD95A => 0x89,
+
+            0xC6BD => 0x14,
+            0xC6BE => 0xA9
+        );
+        let mut memory = CPUMemory::new(box cartridge, vec![]);
+        memory.set(0x0089, 0x00, 0);
+        memory.set(0x008A, 0x03, 0);
+        memory.set(0x0300, 0x89, 0);
+        memory
+    }
+
+    #[test]
+    fn absolute_jumps_should_not_show_the_value_at_the_target() {
+        let cpu = CpuBuilder::new().program_counter(0xC000).build();
+        assert_eq!(
+            "C000  4C F5 C5  JMP $C5F5                      ",
+            disassemble(&cpu, &cpu_memory())
+        );
+    }
+
+    #[test]
+    fn zero_page_should_show_the_value_in_memory() {
+        let cpu = CpuBuilder::new().program_counter(0xC5F7).build();
+        assert_eq!(
+            "C5F7  86 00     STX $00 = 00                   ",
+            disassemble(&cpu, &cpu_memory())
+        );
+    }
+
+    #[test]
+    fn implied() {
+        let cpu = CpuBuilder::new().program_counter(0xC72D).build();
+        assert_eq!(
+            "C72D  EA        NOP                            ",
+            disassemble(&cpu, &cpu_memory())
+        );
+    }
+
+    #[test]
+    fn indirect_y_should_show_base_and_effective_address() {
+        let cpu = CpuBuilder::new().program_counter(0xD959).build();
+        assert_eq!(
+            "D959  B1 89     LDA ($89),Y = 0300 @ 0300 = 89 ",
+            disassemble(&cpu, &cpu_memory())
+        );
+    }
+
+    #[test]
+    fn unofficial_opcodes_should_be_marked() {
+        let cpu = CpuBuilder::new()
+            .program_counter(0xC6BD)
+            .register_x(0x89)
+            .build();
+        assert_eq!(
+            "C6BD  14 A9    *NOP $A9,X @ 32 = 00            ",
+            disassemble(&cpu, &cpu_memory())
+        );
+    }
+}
@@ -0,0 +1,104 @@
+#![feature(box_syntax)]
+#[macro_use]
+extern crate nes;
+
+mod fakes;
+use fakes::controller::FakeController;
+use nes::borrow::MutableRef;
+use nes::cpu;
+use nes::cpu::opcodes;
+use nes::ines::mapper::Mapper;
+use nes::input::standard_controller::StandardController;
+use nes::memory::Memory;
+use nes::ppu::ppumemory::PPUMemory;
+use nes::ppu::screen::ScreenMock;
+use nes::region::Region;
+use nes::NES;
+
+fn nes_playing_one_byte_sample<'a>(
+    controller: &'a FakeController,
+    first_instruction: u8,
+) -> NES<'a, ScreenMock, fakes::audio_device::AudioDevice> {
+    let memory = memory!(
+        0x8000 => first_instruction,
+        0x8001 => opcodes::NOP_IMPLIED,
+        0x8002 => opcodes::NOP_IMPLIED,
+
+        0x9000 => opcodes::NOP_IMPLIED,
+
+        0xA000 => opcodes::NOP_IMPLIED,
+
+        0xC000 => 0xAA,
+
+        0xFFFA => 0x00,
+        0xFFFB => 0xA0,
+        0xFFFC => 0x00,
+        0xFFFD => 0x80,
+        0xFFFE => 0x00,
+        0xFFFF => 0x90
+    );
+    let standard_controller = StandardController::new(controller);
+
+    let mut nes = NES::new(
+        Mapper {
+            cpu_memory: box memory,
+            ppu_memory: PPUMemory::no_mirroring(),
+            region: Region::NTSC,
+        },
+        MutableRef::Box(box standard_controller),
+        fakes::audio_device::AudioDevice {},
+        box ScreenMock::new(),
+    );
+    nes.memory.set(0x4010, 0x8F, 0); //IRQ enabled, highest rate
+    nes.memory.set(0x4012, 0x00, 0); //$C000
+    nes.memory.set(0x4013, 0x00, 0); //1 byte
+    nes.memory.set(0x4015, 0x10, 0);
+    nes
+}
+
+#[test]
+fn sample_fetch_should_stall_the_cpu_and_raise_an_irq() {
+    let controller = FakeController::new();
+    let mut nes = nes_playing_one_byte_sample(&controller, opcodes::CLI);
+
+    nes.execute();
+    assert_eq!(2 + 4 + 7, nes.cycle_count);
+    assert_eq!(0x9000, nes.cpu.program_counter());
+    assert!(nes.cpu.is_flag_set(cpu::INTERRUPT_DISABLE_FLAG));
+    assert_eq!(0x80, nes.memory.get(0x01FF, 0));
+    assert_eq!(0x01, nes.memory.get(0x01FE, 0));
+
+    nes.execute();
+    assert_eq!(2 + 4 + 7 + 2, nes.cycle_count);
+}
+
+#[test]
+fn irq_should_wait_while_interrupts_are_disabled() {
+    let controller = FakeController::new();
+    let mut nes = nes_playing_one_byte_sample(&controller, opcodes::NOP_IMPLIED);
+
+    nes.execute();
+    assert_eq!(2 + 4, nes.cycle_count);
+    assert_eq!(0x8001, nes.cpu.program_counter());
+    assert!(nes.apu.irq());
+
+    assert_eq!(0x80, nes.memory.get(0x4015, 0) & 0xDF);
+    nes.memory.set(0x4015, 0x00, 0); //Acknowledges the IRQ
+    assert!(!nes.apu.irq());
+}
+
+#[test]
+fn nmi_during_the_irq_sequence_should_be_taken_after_it() {
+    let controller = FakeController::new();
+    let mut nes = nes_playing_one_byte_sample(&controller, opcodes::CLI);
+    //NMI on vblank, which starts 27395 cycles in, 4 cycles into the IRQ
+    nes.memory.set(0x2000, 0x80, 0);
+    let mut screen = ScreenMock::new();
+    nes.ppu.borrow_mut().sync(27395 - 10, &mut screen);
+
+    nes.execute();
+    assert_eq!(2 + 4 + 7 + 7, nes.cycle_count);
+    assert_eq!(0xA000, nes.cpu.program_counter());
+    assert_eq!(0x90, nes.memory.get(0x01FC, 0));
+    assert_eq!(0x00, nes.memory.get(0x01FB, 0));
+}
@@ -33,6 +33,7 @@ fn create_ppu(mirroring: Mirroring) -> PPU {
     );
     let mut ppu = PPU::new(PPUMemory::wrap(SharedMemory::wrap(memory), mirroring));
     ppu.set_ppu_ctrl(0x08);
+    ppu.set_ppu_mask(0x1E, 0);
     ppu.load(
         0x0010,
         &[
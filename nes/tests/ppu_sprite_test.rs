@@ -31,6 +31,7 @@ fn create_ppu() -> Rc<RefCell<PPU>> {
         Mirroring::NoMirroring,
     ));
     ppu.set_ppu_ctrl(0x08);
+    ppu.set_ppu_mask(0x1E, 0);
 
     ppu.load(
         0x1010,
@@ -56,7 +57,7 @@ fn create_ppu() -> Rc<RefCell<PPU>> {
     return Rc::new(RefCell::new(ppu));
 }
 
-use nes::memory::{CPUMemory, Memory};
+use nes::memory::{BasicMemory, CPUMemory, Memory};
 use nes::sound::APU;
 
 use screen::{BROWN, GRAY, ORANGE, WHITE};
@@ -75,11 +76,12 @@ fn test_basic_sprite_rendering() {
     );
 
     let mut cpu_memory = CPUMemory::default(
-        box basic_memory,
+        box BasicMemory::new(),
         ppu.clone(),
-        &APU::new(Rc::new(RefCell::new(Vec::new())), 1),
+        &APU::new(Rc::new(RefCell::new(Vec::new())), 1.0),
         None,
     );
+    cpu_memory.set_slice(0x0200, &basic_memory[0x0200..0x0300]);
     {
         cpu_memory.set(0x4014, 0x02, 0);
     };
@@ -164,11 +166,12 @@ fn test_multiple_sprite_rendering() {
 
     {
         let mut cpu_memory = CPUMemory::default(
-            box basic_memory,
+            box BasicMemory::new(),
             ppu.clone(),
-            &APU::new(Rc::new(RefCell::new(Vec::new())), 1),
+            &APU::new(Rc::new(RefCell::new(Vec::new())), 1.0),
             None,
         );
+        cpu_memory.set_slice(0x0200, &basic_memory[0x0200..0x0300]);
         cpu_memory.set(0x4014, 0x02, 0);
     };
 
@@ -220,11 +223,12 @@ fn test_background_sprite() {
 
     {
         let mut cpu_memory = CPUMemory::default(
-            box basic_memory,
+            box BasicMemory::new(),
             ppu.clone(),
-            &APU::new(Rc::new(RefCell::new(Vec::new())), 1),
+            &APU::new(Rc::new(RefCell::new(Vec::new())), 1.0),
             None,
         );
+        cpu_memory.set_slice(0x0200, &basic_memory[0x0200..0x0300]);
         cpu_memory.set(0x4014, 0x02, 0);
     };
 
@@ -262,6 +266,43 @@ fn test_background_sprite() {
     }
 }
 
+#[test]
+fn test_horizontally_flipped_sprite() {
+    let ppu = create_ppu();
+    let mut screen = ScreenMock::new();
+    let basic_memory = memory!(
+        0x0200 => 0x00,
+        0x0201 => 0x02,
+        0x0202 => 0x40, //Flip horizontally
+        0x0203 => 0x00
+    );
+
+    {
+        let mut cpu_memory = CPUMemory::default(
+            box BasicMemory::new(),
+            ppu.clone(),
+            &APU::new(Rc::new(RefCell::new(Vec::new())), 1.0),
+            None,
+        );
+        cpu_memory.set_slice(0x0200, &basic_memory[0x0200..0x0300]);
+        cpu_memory.set(0x4014, 0x02, 0);
+    };
+
+    ppu.borrow_mut().update_screen(&mut screen);
+    {
+        let pixel_buffer = screen.screen_buffer.as_ref();
+
+        screen::assert_pixels(
+            &[GRAY, BROWN, GRAY, GRAY, BROWN, BROWN, GRAY, GRAY],
+            pixel_buffer,
+            {
+                let start = 2 * 256;
+                start..start + 8
+            },
+        );
+    }
+}
+
 #[test]
 #[allow(non_snake_case)]
 fn should_not_render_sprite_at_position_FE_or_FF() {
@@ -282,11 +323,12 @@ fn should_not_render_sprite_at_position_FE_or_FF() {
 
     {
         let mut cpu_memory = CPUMemory::default(
-            box basic_memory,
+            box BasicMemory::new(),
             ppu.clone(),
-            &APU::new(Rc::new(RefCell::new(Vec::new())), 1),
+            &APU::new(Rc::new(RefCell::new(Vec::new())), 1.0),
             None,
         );
+        cpu_memory.set_slice(0x0200, &basic_memory[0x0200..0x0300]);
         cpu_memory.set(0x4014, 0x02, 0);
     };
 
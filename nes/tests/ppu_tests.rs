@@ -31,6 +31,7 @@ fn draw_buffer_from_name_table_1() {
         Mirroring::NoMirroring,
     ));
     ppu.set_ppu_ctrl(0x08);
+    ppu.set_ppu_mask(0x1E, 0);
 
     load_ppu_patterns(&mut ppu, 0x0000);
 
@@ -92,6 +93,7 @@ fn draw_buffer_from_name_table_1_with_high_background_patterns() {
         Mirroring::NoMirroring,
     ));
     ppu.set_ppu_ctrl(0x10);
+    ppu.set_ppu_mask(0x1E, 0);
 
     load_ppu_patterns(&mut ppu, 0x1000);
 
@@ -159,6 +161,7 @@ fn draw_buffer_from_name_table_2() {
     load_ppu_patterns(&mut ppu, 0x0000);
 
     ppu.set_ppu_ctrl(0x08 | 0x01);
+    ppu.set_ppu_mask(0x1E, 0);
 
     let mut screen = ScreenMock::new();
     ppu.update_screen(&mut screen);
@@ -225,6 +228,7 @@ fn draw_buffer_from_name_table_1_with_scrolling_y() {
         Mirroring::NoMirroring,
     ));
     ppu.set_ppu_ctrl(0x08);
+    ppu.set_ppu_mask(0x1E, 0);
     load_ppu_patterns(&mut ppu, 0x0000);
     ppu.set_scroll(0); //x scroll
     ppu.set_scroll(8); //y scroll
@@ -294,6 +298,7 @@ fn draw_buffer_from_name_table_1_with_scrolling_x() {
         Mirroring::NoMirroring,
     ));
     ppu.set_ppu_ctrl(0x08);
+    ppu.set_ppu_mask(0x1E, 0);
     load_ppu_patterns(&mut ppu, 0x0000);
     ppu.set_scroll(8); //x scroll
     ppu.set_scroll(0); //y scroll
@@ -314,6 +319,79 @@ fn draw_buffer_from_name_table_1_with_scrolling_x() {
     }
 }
 
+#[test]
+fn scroll_changes_should_take_effect_on_the_next_scanline() {
+    let memory = memory!(
+        0x3F00 => 0x1F, //Black
+        0x3F01 => 0x20, //White
+        0x3F03 => 0x0B  //(0x00, 0x3F, 0x17)
+    );
+    let mut ppu = PPU::new(PPUMemory::wrap(
+        SharedMemory::wrap(memory),
+        Mirroring::Horizontal,
+    ));
+    load_ppu_patterns(&mut ppu, 0x0000);
+    for row in 0..30 {
+        ppu.load(0x2000 + row * 32, &[0x01]); //pattern 1 in the first column
+    }
+    ppu.set_ppu_mask(0x1E, 0);
+
+    let mut screen = ScreenMock::new();
+    //The first frame is not preceded by a pre-render line
+    sync_to_scanline(&mut ppu, &mut screen, 261);
+    sync_to_scanline(&mut ppu, &mut screen, 16);
+    ppu.set_scroll(8); //x scroll
+    ppu.set_scroll(0); //y scroll
+    sync_to_scanline(&mut ppu, &mut screen, 240);
+    ppu.present(&mut screen);
+    let pixel_buffer = screen.screen_buffer.as_ref();
+
+    //Status bar
+    screen::assert_pixels(
+        &[
+            BACK_DROP, BACK_DROP, BACK_DROP, BROWN, BROWN, BROWN, BACK_DROP, BACK_DROP,
+        ],
+        pixel_buffer,
+        0..8,
+    );
+    screen::assert_pixels(
+        &[
+            BACK_DROP, BACK_DROP, BACK_DROP, BROWN, BROWN, BROWN, BACK_DROP, BACK_DROP,
+        ],
+        pixel_buffer,
+        16 * 256..16 * 256 + 8,
+    );
+
+    //Play field
+    screen::assert_pixels(
+        &[
+            BACK_DROP, BACK_DROP, WHITE, WHITE, BACK_DROP, BACK_DROP, WHITE, BACK_DROP,
+        ],
+        pixel_buffer,
+        17 * 256..17 * 256 + 8,
+    );
+    screen::assert_pixels(
+        &[
+            BACK_DROP, BACK_DROP, BACK_DROP, WHITE, WHITE, WHITE, BACK_DROP, BACK_DROP,
+        ],
+        pixel_buffer,
+        232 * 256..232 * 256 + 8,
+    );
+    screen::assert_pixels(
+        &[
+            BACK_DROP, BACK_DROP, BACK_DROP, BROWN, BROWN, BROWN, BACK_DROP, BACK_DROP,
+        ],
+        pixel_buffer,
+        232 * 256 + 248..232 * 256 + 256,
+    );
+}
+
+fn sync_to_scanline(ppu: &mut PPU, screen: &mut ScreenMock, scanline: u32) {
+    while ppu.scanline() != scanline {
+        ppu.sync(1, screen);
+    }
+}
+
 fn load_ppu_patterns(ppu: &mut PPU, address: u16) {
     ppu.load(
         address,
@@ -31,6 +31,7 @@ fn draw_buffer_from_name_table_1() {
         Mirroring::NoMirroring,
     ));
     ppu.set_ppu_ctrl(0x08);
+    ppu.set_ppu_mask(0x1E, 0);
 
     load_ppu_patterns(&mut ppu);
 
@@ -9,6 +9,7 @@ use nes::ines::mapper::Mapper;
 use nes::input::standard_controller::StandardController;
 use nes::ppu::ppumemory::PPUMemory;
 use nes::ppu::screen::ScreenMock;
+use nes::region::Region;
 use nes::NES;
 
 #[test]
@@ -50,6 +51,7 @@ fn vbl_timing() {
         Mapper {
             cpu_memory: box memory,
             ppu_memory: PPUMemory::no_mirroring(),
+            region: Region::NTSC,
         },
         MutableRef::Box(box standard_controller),
         fakes::audio_device::AudioDevice {},
@@ -134,6 +136,7 @@ fn nmi_timing() {
         Mapper {
             cpu_memory: box memory,
             ppu_memory: PPUMemory::no_mirroring(),
+            region: Region::NTSC,
         },
         MutableRef::Box(box standard_controller),
         fakes::audio_device::AudioDevice {},
@@ -3,7 +3,7 @@
 extern crate nes;
 
 use nes::memory::SharedMemory;
-use nes::memory::{CPUMemory, Memory};
+use nes::memory::{BasicMemory, CPUMemory, Memory};
 use nes::ppu::ppumemory::{Mirroring, PPUMemory};
 use nes::ppu::screen::ScreenMock;
 use nes::ppu::PPU;
@@ -12,9 +12,10 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 #[test]
-#[ignore]
 fn test_basic_sprite_rendering() {
     let ppu = create_ppu();
+    //Sprite 0 hits need both the background and sprites to be shown
+    ppu.borrow_mut().set_ppu_mask(0x1E, 0);
     let mut screen = ScreenMock::new();
     let basic_memory = memory!(
         0x0200 => 0x00,
@@ -24,23 +25,118 @@ fn test_basic_sprite_rendering() {
     );
 
     let mut cpu_memory = CPUMemory::default(
-        box basic_memory,
+        box BasicMemory::new(),
         ppu.clone(),
-        &APU::new(Rc::new(RefCell::new(Vec::new())), 1),
+        &APU::new(Rc::new(RefCell::new(Vec::new())), 1.0),
         None,
     );
+    //$0200 is work RAM on the CPU bus, not the cartridge
+    cpu_memory.set_slice(0x0200, &basic_memory[0x0200..0x0300]);
     {
         cpu_memory.set(0x4014, 0x02, 0);
     };
 
-    //Sprite 0 should hit on pixel 4
-    //i.e first scanline dot 2+4=6 => 6 PPU_CYCLES = 6/3 = 2 CPU_CYCLES
+    //Sprites are drawn one scanline below their Y, so the hit is on scanline 1,
+    //on pixel 2 where row 1 of the background tile starts: dot 1+2=3.
+    //Go to dot 336 of scanline 0 (112 CPU cycles), 3 dots before the end.
+    run(&ppu, 112);
     ppu.borrow_mut().sync(1, &mut screen);
     assert_eq!(0x0, ppu.borrow_mut().status(0) & 0x40);
     ppu.borrow_mut().sync(2, &mut screen);
     assert_eq!(0x40, ppu.borrow_mut().status(0) & 0x40);
 }
 
+#[test]
+fn sprite_0_hit_should_be_cleared_at_the_pre_render_line() {
+    let ppu = create_ppu();
+    set_sprite_zero(&ppu, 0, 0);
+    ppu.borrow_mut().set_ppu_mask(0x1E, 0);
+
+    run(&ppu, 29_781 + 29_781 / 2);
+    assert_eq!(0x40, ppu.borrow_mut().status(0) & 0x40);
+
+    run(&ppu, 29_781 / 2);
+    assert_eq!(0x00, ppu.borrow_mut().status(0) & 0x40);
+}
+
+#[test]
+fn sprite_0_hit_should_not_happen_at_x_255() {
+    let ppu = create_ppu();
+    ppu.borrow_mut().load(0x201F, &[0x02]);
+    ppu.borrow_mut().set_ppu_mask(0x1E, 0);
+    set_sprite_zero(&ppu, 0, 255);
+
+    assert_eq!(0x00, status_after_second_frame(&ppu) & 0x40);
+
+    let ppu = create_ppu();
+    ppu.borrow_mut().load(0x201F, &[0x02]);
+    ppu.borrow_mut().set_ppu_mask(0x1E, 0);
+    set_sprite_zero(&ppu, 0, 254);
+
+    assert_eq!(0x40, status_after_second_frame(&ppu) & 0x40);
+}
+
+#[test]
+fn sprite_0_hit_should_not_happen_in_the_left_most_pixels_when_clipped() {
+    for &mask in [0x18, 0x1A, 0x1C].iter() {
+        let ppu = create_ppu();
+        ppu.borrow_mut().load(0x2000, &[0x02]);
+        ppu.borrow_mut().set_ppu_mask(mask, 0);
+        set_sprite_zero(&ppu, 0, 0);
+
+        assert_eq!(
+            0x00,
+            status_after_second_frame(&ppu) & 0x40,
+            "Mask {:02X}",
+            mask
+        );
+    }
+
+    let ppu = create_ppu();
+    ppu.borrow_mut().load(0x2000, &[0x02, 0x02]);
+    ppu.borrow_mut().set_ppu_mask(0x18, 0);
+    set_sprite_zero(&ppu, 0, 1);
+
+    //Pixel 8 is outside of the clipped area
+    assert_eq!(0x40, status_after_second_frame(&ppu) & 0x40);
+}
+
+#[test]
+fn sprite_0_hit_should_require_both_background_and_sprites() {
+    for &mask in [0x0E, 0x16].iter() {
+        let ppu = create_ppu();
+        ppu.borrow_mut().load(0x2000, &[0x02]);
+        ppu.borrow_mut().set_ppu_mask(mask, 0);
+        set_sprite_zero(&ppu, 0, 0);
+
+        assert_eq!(
+            0x00,
+            status_after_second_frame(&ppu) & 0x40,
+            "Mask {:02X}",
+            mask
+        );
+    }
+}
+
+fn set_sprite_zero(ppu: &Rc<RefCell<PPU>>, y: u8, x: u8) {
+    ppu.borrow_mut().sprites_mut().slice()[0..4].copy_from_slice(&[y, 0x01, 0x00, x]);
+}
+
+/**
+ * Status after the first 16 scanlines of the second frame.
+ */
+fn status_after_second_frame(ppu: &Rc<RefCell<PPU>>) -> u8 {
+    run(ppu, 29_781 + 16 * 114);
+    ppu.borrow_mut().status(0)
+}
+
+fn run(ppu: &Rc<RefCell<PPU>>, cpu_cycles: u32) {
+    let mut screen = ScreenMock::new();
+    for _ in 0..cpu_cycles {
+        ppu.borrow_mut().sync(1, &mut screen);
+    }
+}
+
 fn create_ppu() -> Rc<RefCell<PPU>> {
     let memory = memory!(
         //BG colour palettes
@@ -56,7 +152,7 @@ fn create_ppu() -> Rc<RefCell<PPU>> {
         0x3F15 => 0x17, //(0xCB,0x4F,0x0F)
         0x3F17 => 0x3B, //(0xB3,0xFF,0xCF)
 
-        0x2000 => 0x01
+        0x2000 => 0x01 //pattern 1
     );
     let mut ppu = PPU::new(PPUMemory::wrap(
         SharedMemory::wrap(memory),
@@ -72,6 +168,11 @@ fn create_ppu() -> Rc<RefCell<PPU>> {
             0b00001100, 0b00110010, 0b00111000, 0b00011100, 0b00001110, 0b00100110, 0b00011100,
             0b00000000, //Layer 2
             0b00000000, 0b00000000, 0b00000000, 0b00000000, 0b00000000, 0b00000000, 0b00000000,
+            0b00000000, //Pattern table 2
+            //Layer 1
+            0b11111111, 0b11111111, 0b11111111, 0b11111111, 0b11111111, 0b11111111, 0b11111111,
+            0b11111111, //Layer 2
+            0b00000000, 0b00000000, 0b00000000, 0b00000000, 0b00000000, 0b00000000, 0b00000000,
             0b00000000,
         ],
     );
@@ -4,6 +4,7 @@ use sdl2::Sdl;
 
 pub struct SDLAudioDevice {
     audio_queue: AudioQueue<i16>,
+    sample_rate: u32,
 }
 
 pub fn new_audio_device(sdl_context: &Sdl) -> SDLAudioDevice {
@@ -21,6 +22,7 @@ pub fn new_audio_device(sdl_context: &Sdl) -> SDLAudioDevice {
         .unwrap();
     println!("Spec {:?}", device.spec());
     SDLAudioDevice {
+        sample_rate: device.spec().freq as u32,
         audio_queue: device,
     }
 }
@@ -30,4 +32,12 @@ impl AudioDevice for SDLAudioDevice {
         self.audio_queue.queue(pulse);
         self.audio_queue.resume()
     }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn buffered_samples(&self) -> Option<usize> {
+        Some(self.audio_queue.size() as usize / 2)
+    }
 }
@@ -5,8 +5,10 @@ pub mod standard_controller;
 
 pub use self::audio::SDLAudioDevice;
 pub use self::screen::SDL2Screen;
+pub use self::viewer::Viewers;
 pub mod audio;
 pub mod screen;
+pub mod viewer;
 
 use sdl2::Sdl;
 
@@ -29,6 +31,10 @@ impl SDL2 {
         SDL2Screen::new(&self.0, scale)
     }
 
+    pub fn viewers(&self) -> Viewers {
+        Viewers::new(&self.0)
+    }
+
     pub fn audio(&self) -> SDLAudioDevice {
         audio::new_audio_device(&self.0)
     }
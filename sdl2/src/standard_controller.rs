@@ -6,16 +6,38 @@ use std::rc::Rc;
 
 pub struct SdlEvents(pub Rc<RefCell<EventPump>>);
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HotKey {
+    Exit,
+    Reset,
+    PowerCycle,
+}
+
 impl SdlEvents {
     pub fn should_exit(&self) -> bool {
+        self.hot_keys().contains(&HotKey::Exit)
+    }
+
+    pub fn hot_keys(&self) -> Vec<HotKey> {
+        let mut hot_keys = vec![];
         for event in self.0.borrow_mut().poll_iter() {
             use sdl2::event::Event;
             match event {
-                Event::Quit { .. } => return true,
+                Event::Quit { .. } => hot_keys.push(HotKey::Exit),
+                Event::KeyDown {
+                    scancode: Some(Scancode::F2),
+                    repeat: false,
+                    ..
+                } => hot_keys.push(HotKey::Reset),
+                Event::KeyDown {
+                    scancode: Some(Scancode::F3),
+                    repeat: false,
+                    ..
+                } => hot_keys.push(HotKey::PowerCycle),
                 _ => (),
             }
         }
-        return false;
+        return hot_keys;
     }
 }
 
@@ -0,0 +1,132 @@
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{Renderer, Texture};
+use sdl2::Sdl;
+
+use nes::ppu::screen::PixelBuffer;
+use nes::ppu::viewer;
+use nes::ppu::viewer::Image;
+use nes::ppu::PPU;
+
+/**
+ * A window showing one of the PPU debug images.
+ */
+pub struct ImageWindow<'a> {
+    scale: u8,
+
+    renderer: Renderer<'a>,
+    texture: Texture,
+}
+
+impl<'a> ImageWindow<'a> {
+    pub fn new(
+        sdl_context: &Sdl,
+        title: &str,
+        width: usize,
+        height: usize,
+        scale: u8,
+    ) -> ImageWindow<'a> {
+        let video_subsystem = sdl_context.video().unwrap();
+
+        let width = (width * scale as usize) as u32;
+        let height = (height * scale as usize) as u32;
+        let window = video_subsystem
+            .window(title, width, height)
+            .opengl()
+            .build()
+            .unwrap();
+
+        let renderer = window.renderer().build().unwrap();
+        let texture = renderer
+            .create_texture_streaming(PixelFormatEnum::ARGB8888, width, height)
+            .unwrap();
+
+        ImageWindow {
+            scale: scale,
+            renderer: renderer,
+            texture: texture,
+        }
+    }
+
+    pub fn show(&mut self, image: &Image) {
+        let scale = self.scale;
+        self.texture
+            .with_lock(None, |buf, pitch| {
+                PixelBuffer {
+                    buffer: buf,
+                    pitch: pitch,
+                    scale: scale,
+                }
+                .draw_image(image)
+            })
+            .unwrap();
+
+        self.renderer.clear();
+        self.renderer.copy(&self.texture, None, None).unwrap();
+        self.renderer.present();
+    }
+}
+
+/**
+ * Side windows with the name tables, pattern tables, palette and sprites,
+ * meant to be refreshed once per frame.
+ */
+pub struct Viewers<'a> {
+    name_tables: ImageWindow<'a>,
+    pattern_tables: ImageWindow<'a>,
+    palette: ImageWindow<'a>,
+    sprites: ImageWindow<'a>,
+    last_frame: Option<u32>,
+}
+
+impl<'a> Viewers<'a> {
+    pub fn new(sdl_context: &Sdl) -> Viewers<'a> {
+        Viewers {
+            name_tables: ImageWindow::new(
+                sdl_context,
+                "Name tables",
+                viewer::NAME_TABLES_WIDTH,
+                viewer::NAME_TABLES_HEIGHT,
+                1,
+            ),
+            pattern_tables: ImageWindow::new(
+                sdl_context,
+                "Pattern tables",
+                viewer::PATTERN_TABLES_WIDTH,
+                viewer::PATTERN_TABLES_HEIGHT,
+                2,
+            ),
+            palette: ImageWindow::new(
+                sdl_context,
+                "Palette",
+                viewer::PALETTE_WIDTH,
+                viewer::PALETTE_HEIGHT,
+                2,
+            ),
+            sprites: ImageWindow::new(
+                sdl_context,
+                "Sprites",
+                viewer::SPRITES_WIDTH,
+                viewer::SPRITES_HEIGHT,
+                4,
+            ),
+            last_frame: None,
+        }
+    }
+
+    pub fn show(&mut self, ppu: &PPU) {
+        self.name_tables.show(&viewer::name_tables(ppu));
+        self.pattern_tables.show(&viewer::pattern_tables(ppu, 0));
+        self.palette.show(&viewer::palette(ppu));
+        self.sprites.show(&viewer::sprites(ppu));
+        self.last_frame = Some(ppu.frame_count());
+    }
+
+    /**
+     * Refreshes the windows if the PPU has finished a frame since the last time.
+     */
+    pub fn update(&mut self, ppu: &PPU) {
+        if self.last_frame != Some(ppu.frame_count()) {
+            self.show(ppu);
+        }
+    }
+}